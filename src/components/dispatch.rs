@@ -0,0 +1,213 @@
+use serde::Serialize;
+use std::{fmt::Debug, sync::Mutex};
+use tokio::sync::Notify;
+
+use crate::utils::{
+    atomic::{AtomicOperation, Usize},
+    constants::{DISPATCH_LOAD_PENALTY, DISPATCH_STOP_PENALTY},
+};
+
+use super::enums::{Direction, MovingStatus, OperatingMode};
+
+/// A call the controller needs to assign to a car.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Call {
+    /// Hall button pressed on a floor, any car may answer it.
+    Hall { floor: u8, direction: Direction },
+    /// Inside button pressed in a specific car.
+    Car { elevator_id: u8, floor: u8 },
+}
+
+/// Hall calls a car gave back for the controller to assign again, shared by
+/// the controller and its cars.
+#[derive(Debug, Default)]
+pub struct HallCallQueue {
+    calls: Mutex<Vec<(u8, Direction)>>,
+    notify: Notify,
+}
+
+impl HallCallQueue {
+    pub fn push(&self, floor: u8, direction: Direction) {
+        self.calls.lock().unwrap().push((floor, direction));
+        self.notify.notify_one();
+    }
+
+    /// Takes the calls given back so far, in order.
+    pub fn drain(&self) -> Vec<(u8, Direction)> {
+        std::mem::take(&mut *self.calls.lock().unwrap())
+    }
+
+    /// Waits for calls to be given back, then takes them.
+    pub async fn wait(&self) -> Vec<(u8, Direction)> {
+        loop {
+            let calls = self.drain();
+            if !calls.is_empty() {
+                return calls;
+            }
+            self.notify.notified().await;
+        }
+    }
+}
+
+/// Read-only view of an elevator, taken when a call is dispatched.
+#[derive(Debug, Clone, Serialize)]
+pub struct ElevatorSnapshot {
+    pub id: u8,
+    pub floor: u8,
+    pub moving_status: MovingStatus,
+    pub mode: OperatingMode,
+    /// The car stands still on a fault.
+    pub faulted: bool,
+    /// Floors the car will visit, in order.
+    pub targets: Vec<u8>,
+    /// Share of the car capacity in use.
+    pub load_ratio: f64,
+    /// The car is too full to pick anyone up.
+    pub full_load: bool,
+}
+
+impl ElevatorSnapshot {
+    /// Estimates the cost (floors travelled plus a penalty per intermediate stop
+    /// and for the load) for this car to answer a hall call, following its
+    /// planned targets.
+    pub fn route_cost(&self, floor: u8, direction: Direction) -> u32 {
+        let mut position = self.floor;
+        let mut cost = (self.load_ratio * DISPATCH_LOAD_PENALTY as f64).round() as u32;
+        for &target in &self.targets {
+            // The car passes the hall call on its way, heading the requested direction
+            let passes_by = match direction {
+                Direction::Up => position <= floor && floor <= target,
+                Direction::Down => target <= floor && floor <= position,
+            };
+            if passes_by {
+                return cost + position.abs_diff(floor) as u32;
+            }
+
+            cost += position.abs_diff(target) as u32 + DISPATCH_STOP_PENALTY;
+            position = target;
+        }
+
+        cost + position.abs_diff(floor) as u32
+    }
+}
+
+/// Decides which car answers a call.
+///
+/// Car calls always belong to the car they were made in, strategies only
+/// have to choose a car for hall calls.
+pub trait DispatchStrategy: Debug + Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Returns the id of the car a hall call is assigned to.
+    fn assign_hall_call(
+        &self,
+        floor: u8,
+        direction: Direction,
+        elevators: &[ElevatorSnapshot],
+    ) -> Option<u8>;
+
+    /// Returns the id of the car a call is assigned to.
+    ///
+    /// Hall calls only go to cars in normal operation and free of faults,
+    /// fully loaded cars only get them when every such car is full.
+    fn assign(&self, call: &Call, elevators: &[ElevatorSnapshot]) -> Option<u8> {
+        match *call {
+            Call::Hall { floor, direction } => {
+                let in_service = elevators
+                    .iter()
+                    .filter(|e| e.mode.accepts_hall_calls() && !e.faulted)
+                    .cloned()
+                    .collect::<Vec<ElevatorSnapshot>>();
+                let available = in_service
+                    .iter()
+                    .filter(|e| !e.full_load)
+                    .cloned()
+                    .collect::<Vec<ElevatorSnapshot>>();
+                if available.is_empty() {
+                    self.assign_hall_call(floor, direction, &in_service)
+                } else {
+                    self.assign_hall_call(floor, direction, &available)
+                }
+            }
+            Call::Car { elevator_id, .. } => {
+                elevators.iter().find(|e| e.id == elevator_id).map(|e| e.id)
+            }
+        }
+    }
+}
+
+impl Default for Box<dyn DispatchStrategy> {
+    fn default() -> Self {
+        Box::new(LowestCost)
+    }
+}
+
+/// Assigns the car with the lowest estimated route cost, taking the targets
+/// it already has to serve into account.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LowestCost;
+
+impl DispatchStrategy for LowestCost {
+    fn name(&self) -> &'static str {
+        "lowest_cost"
+    }
+
+    fn assign_hall_call(
+        &self,
+        floor: u8,
+        direction: Direction,
+        elevators: &[ElevatorSnapshot],
+    ) -> Option<u8> {
+        elevators
+            .iter()
+            .min_by_key(|e| e.route_cost(floor, direction))
+            .map(|e| e.id)
+    }
+}
+
+/// Assigns the car closest to the calling floor, preferring idle cars.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NearestCar;
+
+impl DispatchStrategy for NearestCar {
+    fn name(&self) -> &'static str {
+        "nearest_car"
+    }
+
+    fn assign_hall_call(
+        &self,
+        floor: u8,
+        _direction: Direction,
+        elevators: &[ElevatorSnapshot],
+    ) -> Option<u8> {
+        elevators
+            .iter()
+            .min_by_key(|e| (!e.targets.is_empty(), e.floor.abs_diff(floor)))
+            .map(|e| e.id)
+    }
+}
+
+/// Assigns hall calls to every car in turn, regardless of their state.
+#[derive(Debug, Default)]
+pub struct RoundRobin {
+    next: Usize,
+}
+
+impl DispatchStrategy for RoundRobin {
+    fn name(&self) -> &'static str {
+        "round_robin"
+    }
+
+    fn assign_hall_call(
+        &self,
+        _floor: u8,
+        _direction: Direction,
+        elevators: &[ElevatorSnapshot],
+    ) -> Option<u8> {
+        if elevators.is_empty() {
+            return None;
+        }
+
+        Some(elevators[self.next.add(1) % elevators.len()].id)
+    }
+}
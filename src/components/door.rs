@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+
+use crate::utils::constants::{
+    DEFAULT_DOOR_CLOSE_MS, DEFAULT_DOOR_DWELL_MS, DEFAULT_DOOR_MAX_REOPENS,
+    DEFAULT_DOOR_NUDGING_CLOSE_MS, DEFAULT_DOOR_OPEN_MS,
+};
+
+use super::enums::DoorStatus;
+
+/// Door operator timings, in milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DoorTiming {
+    /// Time the doors take to open fully.
+    pub open_ms: u64,
+    /// Time the doors take to close fully.
+    pub close_ms: u64,
+    /// Time the doors stay fully open before closing on their own.
+    pub dwell_ms: u64,
+    /// Time the doors take to close fully while nudging.
+    pub nudging_close_ms: u64,
+    /// How many times an obstruction may reopen the doors before they start nudging.
+    pub max_reopens: u8,
+}
+
+impl Default for DoorTiming {
+    fn default() -> Self {
+        Self {
+            open_ms: DEFAULT_DOOR_OPEN_MS,
+            close_ms: DEFAULT_DOOR_CLOSE_MS,
+            dwell_ms: DEFAULT_DOOR_DWELL_MS,
+            nudging_close_ms: DEFAULT_DOOR_NUDGING_CLOSE_MS,
+            max_reopens: DEFAULT_DOOR_MAX_REOPENS,
+        }
+    }
+}
+
+impl DoorTiming {
+    /// Time the doors take to close fully, slower while nudging.
+    pub fn closing_ms(&self, nudging: bool) -> u64 {
+        if nudging {
+            self.nudging_close_ms
+        } else {
+            self.close_ms
+        }
+    }
+
+    /// How long the doors stay in a status before moving on by themselves.
+    pub fn duration(&self, status: DoorStatus, nudging: bool) -> Option<u64> {
+        match status {
+            DoorStatus::Opening => Some(self.open_ms),
+            DoorStatus::Open => Some(self.dwell_ms),
+            DoorStatus::Closing => Some(self.closing_ms(nudging)),
+            DoorStatus::Closed => None,
+        }
+    }
+
+    /// Time already spent in the new motion when the doors reverse halfway,
+    /// so reopening a half closed door only takes half the opening time.
+    pub fn reversed_elapsed(&self, from: DoorStatus, elapsed: u64, nudging: bool) -> u64 {
+        let (from_ms, to_ms) = match from {
+            DoorStatus::Opening => (self.open_ms, self.closing_ms(nudging)),
+            DoorStatus::Closing => (self.closing_ms(nudging), self.open_ms),
+            _ => return 0,
+        };
+        if from_ms == 0 {
+            return 0;
+        }
+
+        let remaining = from_ms.saturating_sub(elapsed);
+        to_ms * remaining / from_ms
+    }
+}
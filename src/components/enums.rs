@@ -6,18 +6,37 @@ pub enum Direction {
     Down,
 }
 
+impl Direction {
+    /// Sign of the direction along the shaft, positive going up.
+    pub fn sign(self) -> f64 {
+        match self {
+            Self::Up => 1.0,
+            Self::Down => -1.0,
+        }
+    }
+}
+
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
 pub enum DoorStatus {
-    #[default]
-    Close,
+    Opening,
     Open,
+    Closing,
+    #[default]
+    Closed,
+}
+
+impl DoorStatus {
+    /// Returns true if the doors are open or on their way to open.
+    pub fn is_opening(self) -> bool {
+        matches!(self, Self::Opening | Self::Open)
+    }
 }
 
 impl From<bool> for DoorStatus {
     fn from(value: bool) -> Self {
         match value {
             true => Self::Open,
-            false => Self::Close,
+            false => Self::Closed,
         }
     }
 }
@@ -29,3 +48,62 @@ pub enum MovingStatus {
     Up,
     Down,
 }
+
+impl MovingStatus {
+    pub fn direction(self) -> Option<Direction> {
+        match self {
+            Self::None => None,
+            Self::Up => Some(Direction::Up),
+            Self::Down => Some(Direction::Down),
+        }
+    }
+}
+
+impl From<Direction> for MovingStatus {
+    fn from(value: Direction) -> Self {
+        match value {
+            Direction::Up => Self::Up,
+            Direction::Down => Self::Down,
+        }
+    }
+}
+
+/// How a car is operated, only in normal operation does it serve the hall calls.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+pub enum OperatingMode {
+    #[default]
+    Normal,
+    /// Independent service, the car is locked to manual use and only answers
+    /// its own car calls, its doors stay open at each floor until the next one.
+    Independent,
+    /// Inspection by a technician, the car takes no calls at all.
+    Inspection,
+    /// Taken out of service, the car delivers the passengers inside and then
+    /// takes no more calls.
+    OutOfService,
+    /// Fire service Phase I, the car returns non-stop to the recall floor and
+    /// parks there with its doors open.
+    FireRecall,
+    /// Fire service Phase II, a firefighter operates the car from inside, the
+    /// doors only move while their button is held.
+    Firefighter,
+}
+
+impl OperatingMode {
+    pub fn accepts_hall_calls(self) -> bool {
+        self == Self::Normal
+    }
+
+    pub fn accepts_car_calls(self) -> bool {
+        matches!(self, Self::Normal | Self::Independent | Self::Firefighter)
+    }
+
+    /// Returns true if passengers waiting at a floor get into the car.
+    pub fn boards_passengers(self) -> bool {
+        matches!(self, Self::Normal | Self::Independent)
+    }
+
+    pub fn is_fire_service(self) -> bool {
+        matches!(self, Self::FireRecall | Self::Firefighter)
+    }
+}
@@ -1,6 +1,16 @@
 use serde::Serialize;
 
+use super::{
+    enums::{Direction, OperatingMode},
+    fault::FaultCode,
+};
+
+// Errors serialize as their stable `code` next to the values they are about,
+// wrapped errors are flattened so a client sees the same object however deep
+// the error was raised.
+
 #[derive(thiserror::Error, Debug, Serialize)]
+#[serde(tag = "code", rename_all = "snake_case")]
 pub enum ElevatorError {
     #[error("request error")]
     RequestError,
@@ -8,40 +18,168 @@ pub enum ElevatorError {
     CannotControlDoor,
     #[error("already started")]
     AlreadyStarted,
+    #[error("the elevator runs on a manual clock")]
+    ManualClock,
     #[error("signal not exists")]
+    #[serde(rename = "not_started")]
     SignalNotExists,
-    #[error("already max floor")]
-    AlreadyMaxFloor,
-    #[error("already min floor")]
-    AlreadyMinFloor,
-    #[error("floor button not exists")]
-    FloorButtonNotExists,
+    #[error("already max floor {floor}")]
+    AlreadyMaxFloor { floor: u8 },
+    #[error("already min floor {floor}")]
+    AlreadyMinFloor { floor: u8 },
+    /// The floor is over the top floor, the limit.
+    #[error("floor button {floor} not exists, the top floor is {limit}")]
+    #[serde(rename = "invalid_floor")]
+    FloorButtonNotExists { floor: u8, limit: u8 },
+    #[error("nothing to cancel at floor {floor}")]
+    NothingToCancel { floor: u8 },
+    #[error("hall call {direction:?} at floor {floor} is assigned to another car")]
+    HallCallAssigned { floor: u8, direction: Direction },
+    #[error("invalid motion profile")]
+    InvalidMotionProfile,
+    #[error("cannot change the motion profile while running")]
+    CannotChangeMotionProfile,
+    /// The current mode refuses the action, or the switch to the requested mode.
+    #[error("not available in the current operating mode {mode:?}{}", requested_mode(.requested))]
+    OperatingModeRejected {
+        mode: OperatingMode,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        requested: Option<OperatingMode>,
+    },
+    #[error("stopped on a fault {fault:?}, reset it first")]
+    Faulted { fault: FaultCode },
+    #[error("no fault to reset")]
+    NotFaulted,
+}
+
+fn requested_mode(requested: &Option<OperatingMode>) -> String {
+    requested
+        .map(|mode| format!(", cannot switch to {mode:?}"))
+        .unwrap_or_default()
+}
+
+impl ElevatorError {
+    /// Stable identifier of the error, the `code` it serializes with.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::RequestError => "request_error",
+            Self::CannotControlDoor => "cannot_control_door",
+            Self::AlreadyStarted => "already_started",
+            Self::ManualClock => "manual_clock",
+            Self::SignalNotExists => "not_started",
+            Self::AlreadyMaxFloor { .. } => "already_max_floor",
+            Self::AlreadyMinFloor { .. } => "already_min_floor",
+            Self::FloorButtonNotExists { .. } => "invalid_floor",
+            Self::NothingToCancel { .. } => "nothing_to_cancel",
+            Self::HallCallAssigned { .. } => "hall_call_assigned",
+            Self::InvalidMotionProfile => "invalid_motion_profile",
+            Self::CannotChangeMotionProfile => "cannot_change_motion_profile",
+            Self::OperatingModeRejected { .. } => "operating_mode_rejected",
+            Self::Faulted { .. } => "faulted",
+            Self::NotFaulted => "not_faulted",
+        }
+    }
 }
 
 #[derive(thiserror::Error, Debug, Serialize)]
+#[serde(tag = "code", rename_all = "snake_case")]
 pub enum ElevatorControllerError {
-    #[error("invalid elevator id")]
-    GetElevatorError,
-    #[error("ElevatorError: {}", .0)]
+    /// The car does not exist, the limit is the highest car id.
+    #[error("invalid elevator id {elevator_id}, the highest is {limit}")]
+    #[serde(rename = "elevator_not_found")]
+    GetElevatorError { elevator_id: u8, limit: u8 },
+    #[error("no elevator available")]
+    NoElevatorAvailable,
+    #[error("the elevators run on a real-time clock")]
+    RealtimeClock,
+    #[error("invalid passenger, origin and destination must be different floors")]
+    InvalidPassenger { origin: u8, destination: u8 },
+    #[error(transparent)]
+    #[serde(untagged)]
     ElevatorError(#[from] ElevatorError),
 }
 
+impl ElevatorControllerError {
+    /// Stable identifier of the error, the one of the wrapped error if any.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::GetElevatorError { .. } => "elevator_not_found",
+            Self::NoElevatorAvailable => "no_elevator_available",
+            Self::RealtimeClock => "realtime_clock",
+            Self::InvalidPassenger { .. } => "invalid_passenger",
+            Self::ElevatorError(err) => err.code(),
+        }
+    }
+}
+
 #[derive(thiserror::Error, Debug, Serialize)]
+#[serde(tag = "code", rename_all = "snake_case")]
 pub enum BuildingError {
-    #[error("invalid floor number")]
-    GetFloorError,
-    #[error("FloorError: {}", .0)]
+    /// The floor does not exist, the limit is the top floor.
+    #[error("invalid floor number {floor}, the top floor is {limit}")]
+    #[serde(rename = "floor_not_found")]
+    GetFloorError { floor: u8, limit: u8 },
+    #[error(transparent)]
+    #[serde(untagged)]
     FloorError(#[from] FloorError),
-    #[error("ElevatorControllerError: {}", .0)]
+    #[error(transparent)]
+    #[serde(untagged)]
     ElevatorControllerError(#[from] ElevatorControllerError),
 }
 
+impl BuildingError {
+    /// Stable identifier of the error, the one of the wrapped error if any.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::GetFloorError { .. } => "floor_not_found",
+            Self::FloorError(err) => err.code(),
+            Self::ElevatorControllerError(err) => err.code(),
+        }
+    }
+}
+
 #[derive(thiserror::Error, Debug, Serialize)]
+#[serde(tag = "code", rename_all = "snake_case")]
 pub enum FloorError {
-    #[error("invalid elevator id")]
-    GetPanelError,
-    #[error("ElevatorError: {}", .0)]
+    /// The car does not exist, the limit is the highest car id.
+    #[error("invalid elevator id {elevator_id}, the highest is {limit}")]
+    #[serde(rename = "elevator_not_found")]
+    GetPanelError { elevator_id: u8, limit: u8 },
+    #[error(transparent)]
+    #[serde(untagged)]
     ElevatorError(#[from] ElevatorError),
-    #[error("ElevatorControllerError: {}", .0)]
+    #[error(transparent)]
+    #[serde(untagged)]
     ElevatorControllerError(#[from] ElevatorControllerError),
 }
+
+impl FloorError {
+    /// Stable identifier of the error, the one of the wrapped error if any.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::GetPanelError { .. } => "elevator_not_found",
+            Self::ElevatorError(err) => err.code(),
+            Self::ElevatorControllerError(err) => err.code(),
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug, Serialize)]
+#[serde(tag = "code", rename_all = "snake_case")]
+pub enum SimulationError {
+    #[error("invalid arrival, origin and destination must be different existing floors")]
+    InvalidArrival { origin: u8, destination: u8 },
+    #[error(transparent)]
+    #[serde(untagged)]
+    ElevatorControllerError(#[from] ElevatorControllerError),
+}
+
+impl SimulationError {
+    /// Stable identifier of the error, the one of the wrapped error if any.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::InvalidArrival { .. } => "invalid_arrival",
+            Self::ElevatorControllerError(err) => err.code(),
+        }
+    }
+}
@@ -0,0 +1,239 @@
+use serde::Serialize;
+use std::{collections::VecDeque, sync::Mutex};
+use tokio::sync::broadcast;
+
+use crate::utils::constants::EVENT_HISTORY_LEN;
+
+use super::{
+    enums::{Direction, OperatingMode},
+    fault::FaultCode,
+};
+
+/// A transition of a car, or of the whole building for the fire alarm.
+///
+/// Calls carry the direction of the hall button, none for a car call.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type")]
+pub enum ElevatorEvent {
+    CallRegistered {
+        elevator_id: u8,
+        floor: u8,
+        direction: Option<Direction>,
+    },
+    CallCancelled {
+        elevator_id: u8,
+        floor: u8,
+        direction: Option<Direction>,
+    },
+    CarDeparted {
+        elevator_id: u8,
+        floor: u8,
+        direction: Direction,
+        target_floor: u8,
+    },
+    /// The position indicator of a travelling car moved to another floor.
+    FloorPassed {
+        elevator_id: u8,
+        floor: u8,
+    },
+    FloorArrived {
+        elevator_id: u8,
+        floor: u8,
+    },
+    DoorOpening {
+        elevator_id: u8,
+        floor: u8,
+    },
+    DoorOpened {
+        elevator_id: u8,
+        floor: u8,
+    },
+    DoorClosing {
+        elevator_id: u8,
+        floor: u8,
+    },
+    DoorClosed {
+        elevator_id: u8,
+        floor: u8,
+    },
+    /// The light curtain across the doorway was interrupted or cleared.
+    DoorObstructed {
+        elevator_id: u8,
+        obstructed: bool,
+    },
+    /// The doors gave up on the light curtain and close slowly.
+    DoorNudging {
+        elevator_id: u8,
+    },
+    /// The load went over the rated capacity or back under it, the buzzer
+    /// sounds while the car is overloaded.
+    Overloaded {
+        elevator_id: u8,
+        overloaded: bool,
+    },
+    PassengerBoarded {
+        elevator_id: u8,
+        passenger_id: u64,
+        floor: u8,
+    },
+    PassengerAlighted {
+        elevator_id: u8,
+        passenger_id: u64,
+        floor: u8,
+    },
+    ModeChanged {
+        elevator_id: u8,
+        mode: OperatingMode,
+    },
+    Fault {
+        elevator_id: u8,
+        code: FaultCode,
+    },
+    FaultReset {
+        elevator_id: u8,
+    },
+    /// The fire alarm of the building was raised or cleared.
+    FireAlarm {
+        active: bool,
+    },
+}
+
+impl ElevatorEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::CallRegistered { .. } => "CallRegistered",
+            Self::CallCancelled { .. } => "CallCancelled",
+            Self::CarDeparted { .. } => "CarDeparted",
+            Self::FloorPassed { .. } => "FloorPassed",
+            Self::FloorArrived { .. } => "FloorArrived",
+            Self::DoorOpening { .. } => "DoorOpening",
+            Self::DoorOpened { .. } => "DoorOpened",
+            Self::DoorClosing { .. } => "DoorClosing",
+            Self::DoorClosed { .. } => "DoorClosed",
+            Self::DoorObstructed { .. } => "DoorObstructed",
+            Self::DoorNudging { .. } => "DoorNudging",
+            Self::Overloaded { .. } => "Overloaded",
+            Self::PassengerBoarded { .. } => "PassengerBoarded",
+            Self::PassengerAlighted { .. } => "PassengerAlighted",
+            Self::ModeChanged { .. } => "ModeChanged",
+            Self::Fault { .. } => "Fault",
+            Self::FaultReset { .. } => "FaultReset",
+            Self::FireAlarm { .. } => "FireAlarm",
+        }
+    }
+
+    /// The car the event happened to, none for the building.
+    pub fn elevator_id(&self) -> Option<u8> {
+        match *self {
+            Self::CallRegistered { elevator_id, .. }
+            | Self::CallCancelled { elevator_id, .. }
+            | Self::CarDeparted { elevator_id, .. }
+            | Self::FloorPassed { elevator_id, .. }
+            | Self::FloorArrived { elevator_id, .. }
+            | Self::DoorOpening { elevator_id, .. }
+            | Self::DoorOpened { elevator_id, .. }
+            | Self::DoorClosing { elevator_id, .. }
+            | Self::DoorClosed { elevator_id, .. }
+            | Self::DoorObstructed { elevator_id, .. }
+            | Self::DoorNudging { elevator_id }
+            | Self::Overloaded { elevator_id, .. }
+            | Self::PassengerBoarded { elevator_id, .. }
+            | Self::PassengerAlighted { elevator_id, .. }
+            | Self::ModeChanged { elevator_id, .. }
+            | Self::Fault { elevator_id, .. }
+            | Self::FaultReset { elevator_id } => Some(elevator_id),
+            Self::FireAlarm { .. } => None,
+        }
+    }
+}
+
+/// An event numbered in the order it was published.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EventRecord {
+    pub id: u64,
+    pub at_ms: u64,
+    #[serde(flatten)]
+    pub event: ElevatorEvent,
+}
+
+#[derive(Debug, Default)]
+struct EventHistory {
+    next_id: u64,
+    records: VecDeque<EventRecord>,
+}
+
+/// Publishes the events of the cars to every subscriber, keeping the latest
+/// ones so a subscriber can catch up on what it missed.
+#[derive(Debug)]
+pub struct EventBus {
+    sender: broadcast::Sender<EventRecord>,
+    history: Mutex<EventHistory>,
+    history_len: usize,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new(EVENT_HISTORY_LEN)
+    }
+}
+
+impl EventBus {
+    /// Creates a bus keeping the latest `history_len` events.
+    pub fn new(history_len: usize) -> Self {
+        Self {
+            sender: broadcast::channel(history_len.max(1)).0,
+            history: Default::default(),
+            history_len,
+        }
+    }
+
+    pub fn publish(&self, event: ElevatorEvent, now: u64) {
+        let mut history = self.history.lock().unwrap();
+        history.next_id += 1;
+        let record = EventRecord {
+            id: history.next_id,
+            at_ms: now,
+            event,
+        };
+
+        history.records.push_back(record.clone());
+        while history.records.len() > self.history_len {
+            history.records.pop_front();
+        }
+        // Nobody listening is fine, the history keeps the event
+        let _ = self.sender.send(record);
+    }
+
+    /// Receives the events published from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<EventRecord> {
+        self.sender.subscribe()
+    }
+
+    /// Lists the kept events published after the one with the given id.
+    pub fn since(&self, id: u64) -> Vec<EventRecord> {
+        self.history
+            .lock()
+            .unwrap()
+            .records
+            .iter()
+            .filter(|record| record.id > id)
+            .cloned()
+            .collect()
+    }
+
+    /// Subscribes and replays the kept events published after the one with
+    /// the given id, none are missed or received twice in between.
+    ///
+    /// Events too old to be kept are lost, the replay starts at the oldest one.
+    pub fn resume(&self, id: u64) -> (Vec<EventRecord>, broadcast::Receiver<EventRecord>) {
+        let history = self.history.lock().unwrap();
+        let receiver = self.sender.subscribe();
+        let replay = history
+            .records
+            .iter()
+            .filter(|record| record.id > id)
+            .cloned()
+            .collect();
+        (replay, receiver)
+    }
+}
@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+
+/// Fault latched by a car, it stays put until the fault is reset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+pub enum FaultCode {
+    /// The emergency stop button was pressed.
+    EmergencyStop,
+    /// The doors jammed, they no longer move. A running car still brakes to
+    /// the next floor it can stop at, where it stays with the doors shut.
+    DoorStuck,
+    /// The drive lost power, the brake holds the car.
+    MotorFailure,
+    /// The car no longer knows where it is, a reset runs it down to the
+    /// lowest floor to find its position again.
+    PositionLost,
+    /// The floor sensor reads a different floor on every self check.
+    SensorFlapping,
+    /// The car got stuck between floors, a car standing level at a floor
+    /// slips halfway to the next one.
+    StuckBetweenFloors,
+}
@@ -0,0 +1,44 @@
+use serde::{Deserialize, Serialize};
+
+use crate::utils::constants::{
+    DEFAULT_FULL_LOAD_RATIO, DEFAULT_RATED_LOAD_KG, DEFAULT_RATED_PERSONS,
+};
+
+/// Rated capacity of a car, checked against the load weighing device.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CarCapacity {
+    /// Rated load, in kg.
+    pub rated_load_kg: f64,
+    /// Maximum number of persons allowed in the car.
+    pub rated_persons: u8,
+    /// Share of the rated load above which the car passes hall calls by.
+    pub full_load_ratio: f64,
+}
+
+impl Default for CarCapacity {
+    fn default() -> Self {
+        Self {
+            rated_load_kg: DEFAULT_RATED_LOAD_KG,
+            rated_persons: DEFAULT_RATED_PERSONS,
+            full_load_ratio: DEFAULT_FULL_LOAD_RATIO,
+        }
+    }
+}
+
+impl CarCapacity {
+    /// Share of the capacity in use, by weight or by persons, whichever is higher.
+    pub fn load_ratio(&self, load_kg: f64, persons: u8) -> f64 {
+        let by_weight = load_kg / self.rated_load_kg.max(f64::EPSILON);
+        let by_persons = persons as f64 / self.rated_persons.max(1) as f64;
+        by_weight.max(by_persons)
+    }
+
+    pub fn is_overloaded(&self, load_kg: f64, persons: u8) -> bool {
+        load_kg > self.rated_load_kg || persons > self.rated_persons
+    }
+
+    /// Returns true if the car is too full to pick anyone up.
+    pub fn is_full_load(&self, load_kg: f64, persons: u8) -> bool {
+        self.load_ratio(load_kg, persons) >= self.full_load_ratio
+    }
+}
@@ -0,0 +1,228 @@
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::Mutex,
+};
+
+use crate::utils::constants::{HALL_CALL_WAIT_SAMPLES_LEN, TRAFFIC_RATE_PERIOD_MS};
+
+use super::{enums::Direction, passenger::Passenger};
+
+/// Trips a car finished, and the time they took in milliseconds.
+#[derive(Debug, Clone, Copy, Default)]
+struct TripTotals {
+    trips: usize,
+    travel_ms: u64,
+}
+
+#[derive(Debug, Default)]
+struct MetricsData {
+    since_ms: u64,
+    /// When the hall calls not answered yet were registered.
+    pending_hall_calls: BTreeMap<(u8, Direction), u64>,
+    hall_calls_answered: usize,
+    /// Latest hall call waits, oldest first.
+    hall_call_waits: VecDeque<u64>,
+    /// Time each travelling car departed.
+    departures: BTreeMap<u8, u64>,
+    trips: BTreeMap<u8, TripTotals>,
+}
+
+/// Records hall calls and car trips as they happen, shared by the controller
+/// and its cars.
+#[derive(Debug, Default)]
+pub struct MetricsCollector {
+    data: Mutex<MetricsData>,
+}
+
+impl MetricsCollector {
+    /// Forgets everything recorded, measuring starts over from now.
+    pub fn reset(&self, now: u64) {
+        *self.data.lock().unwrap() = MetricsData {
+            since_ms: now,
+            ..Default::default()
+        };
+    }
+
+    /// A hall call was registered, calling a floor again before it is answered
+    /// keeps the first registration time.
+    pub fn hall_call_registered(&self, floor: u8, direction: Direction, now: u64) {
+        self.data
+            .lock()
+            .unwrap()
+            .pending_hall_calls
+            .entry((floor, direction))
+            .or_insert(now);
+    }
+
+    pub fn hall_call_cancelled(&self, floor: u8, direction: Direction) {
+        self.data
+            .lock()
+            .unwrap()
+            .pending_hall_calls
+            .remove(&(floor, direction));
+    }
+
+    /// A car opened its doors for a hall call.
+    pub fn hall_call_answered(&self, floor: u8, direction: Direction, now: u64) {
+        let mut data = self.data.lock().unwrap();
+        if let Some(registered) = data.pending_hall_calls.remove(&(floor, direction)) {
+            data.hall_calls_answered += 1;
+            data.hall_call_waits
+                .push_back(now.saturating_sub(registered));
+            if data.hall_call_waits.len() > HALL_CALL_WAIT_SAMPLES_LEN {
+                data.hall_call_waits.pop_front();
+            }
+        }
+    }
+
+    pub fn trip_started(&self, elevator_id: u8, now: u64) {
+        self.data
+            .lock()
+            .unwrap()
+            .departures
+            .insert(elevator_id, now);
+    }
+
+    pub fn trip_finished(&self, elevator_id: u8, now: u64) {
+        let mut data = self.data.lock().unwrap();
+        if let Some(departed_ms) = data.departures.remove(&elevator_id) {
+            let totals = data.trips.entry(elevator_id).or_default();
+            totals.trips += 1;
+            totals.travel_ms += now.saturating_sub(departed_ms);
+        }
+    }
+
+    /// Computes the service statistics up to now.
+    ///
+    /// Journey times and handling capacity come from the passengers who
+    /// reached their destination, the hall call waiting time from the latest
+    /// [`HALL_CALL_WAIT_SAMPLES_LEN`] answered calls.
+    pub fn stats(&self, elevator_ids: &[u8], passengers: &[Passenger], now: u64) -> ServiceStats {
+        let data = self.data.lock().unwrap();
+        let elapsed_ms = now.saturating_sub(data.since_ms);
+
+        let arrived = passengers
+            .iter()
+            .filter(|p| p.is_arrived() && p.arrived_ms >= data.since_ms)
+            .collect::<Vec<&Passenger>>();
+        let mut alighted = arrived
+            .iter()
+            .filter_map(|p| p.alighted_ms)
+            .collect::<Vec<u64>>();
+        alighted.sort_unstable();
+        // Most passengers delivered within any 5 minutes
+        let handling_capacity = alighted
+            .iter()
+            .enumerate()
+            .map(|(i, &start)| {
+                alighted[i..].partition_point(|&t| t < start + TRAFFIC_RATE_PERIOD_MS)
+            })
+            .max()
+            .unwrap_or_default();
+
+        let elevators = elevator_ids
+            .iter()
+            .map(|&id| {
+                let totals = data.trips.get(&id).copied().unwrap_or_default();
+                let ongoing = data
+                    .departures
+                    .get(&id)
+                    .map(|&departed| now.saturating_sub(departed))
+                    .unwrap_or_default();
+                let travel_ms = totals.travel_ms + ongoing;
+
+                CarStats {
+                    elevator_id: id,
+                    trips: totals.trips,
+                    travel_ms,
+                    utilisation: if elapsed_ms == 0 {
+                        0.0
+                    } else {
+                        (travel_ms as f64 / elapsed_ms as f64).min(1.0)
+                    },
+                }
+            })
+            .collect();
+
+        ServiceStats {
+            elapsed_ms,
+            hall_calls_answered: data.hall_calls_answered,
+            hall_calls_pending: data.pending_hall_calls.len(),
+            waiting_time: TimeStats::from_samples(data.hall_call_waits.iter().copied().collect()),
+            passengers_delivered: arrived.len(),
+            passenger_waiting_time: TimeStats::from_samples(
+                arrived.iter().filter_map(|p| p.wait_ms()).collect(),
+            ),
+            journey_time: TimeStats::from_samples(
+                arrived.iter().filter_map(|p| p.journey_ms()).collect(),
+            ),
+            handling_capacity,
+            elevators,
+        }
+    }
+}
+
+/// Summary of a set of durations, in milliseconds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct TimeStats {
+    pub count: usize,
+    pub average_ms: f64,
+    pub p50_ms: u64,
+    pub p90_ms: u64,
+    pub p95_ms: u64,
+    pub max_ms: u64,
+}
+
+impl TimeStats {
+    pub fn from_samples(mut samples: Vec<u64>) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+        samples.sort_unstable();
+
+        Self {
+            count: samples.len(),
+            average_ms: samples.iter().sum::<u64>() as f64 / samples.len() as f64,
+            p50_ms: percentile(&samples, 50),
+            p90_ms: percentile(&samples, 90),
+            p95_ms: percentile(&samples, 95),
+            max_ms: samples[samples.len() - 1],
+        }
+    }
+}
+
+/// Nearest rank percentile of sorted samples.
+fn percentile(sorted: &[u64], percent: usize) -> u64 {
+    let rank = (sorted.len() * percent).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CarStats {
+    pub elevator_id: u8,
+    pub trips: usize,
+    /// Time spent travelling, in milliseconds.
+    pub travel_ms: u64,
+    /// Share of the measured time spent travelling.
+    pub utilisation: f64,
+}
+
+/// Service quality of the elevator group.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ServiceStats {
+    /// Time measured since the statistics were reset, in milliseconds.
+    pub elapsed_ms: u64,
+    pub hall_calls_answered: usize,
+    pub hall_calls_pending: usize,
+    /// From registering a hall call to a car opening its doors for it.
+    pub waiting_time: TimeStats,
+    pub passengers_delivered: usize,
+    /// From a passenger showing up to boarding a car.
+    pub passenger_waiting_time: TimeStats,
+    /// From a passenger showing up to alighting at the destination.
+    pub journey_time: TimeStats,
+    /// Most passengers delivered within any 5 minutes.
+    pub handling_capacity: usize,
+    pub elevators: Vec<CarStats>,
+}
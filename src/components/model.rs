@@ -1,10 +1,12 @@
 use serde::{ser::SerializeStruct, Serialize};
 
-use crate::{components::error::BuildingError, Elevator, ElevatorController, OutSideButton};
+use crate::{
+    components::error::BuildingError, Elevator, ElevatorController, OutSideButton, Registration,
+};
 
 use std::{collections::BTreeMap, sync::Arc};
 
-use super::{enums::Direction, error::FloorError};
+use super::{enums::Direction, error::FloorError, passenger::Passenger};
 
 #[derive(Debug, Clone)]
 pub struct Building {
@@ -32,12 +34,29 @@ impl Building {
     }
 
     pub fn get_floor(&self, floor: u8) -> Result<&Floor, BuildingError> {
-        self.floors.get(&floor).ok_or(BuildingError::GetFloorError)
+        self.floors.get(&floor).ok_or(BuildingError::GetFloorError {
+            floor,
+            limit: self.floors.len().saturating_sub(1) as u8,
+        })
     }
 
     pub fn get_eleavtor(&self, elevator_id: u8) -> Result<&Arc<Elevator>, BuildingError> {
         Ok(self.controller.get_elevator(elevator_id)?)
     }
+
+    /// A passenger shows up at the origin floor, see [`Floor::add_passenger`].
+    pub async fn add_passenger(
+        &self,
+        origin: u8,
+        destination: u8,
+    ) -> Result<Passenger, BuildingError> {
+        Ok(self.get_floor(origin)?.add_passenger(destination).await?)
+    }
+
+    /// Fire alarm input, raising it recalls every car to the recall floor.
+    pub async fn fire_alarm(&self, active: bool) -> Result<(), BuildingError> {
+        Ok(self.controller.set_fire_alarm(active).await?)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -69,7 +88,7 @@ impl Floor {
         &self,
         elevator_id: u8,
         direction: Direction,
-    ) -> Result<(), FloorError> {
+    ) -> Result<Registration, FloorError> {
         Ok(self
             .controller
             .get_elevator(elevator_id)?
@@ -77,7 +96,18 @@ impl Floor {
             .await?)
     }
 
-    pub fn get_outside_button(&self, elevator_id: u8) -> Result<&OutSideButton, FloorError> {
-        Ok(self.controller.get_outside_button(elevator_id, self.id)?)
+    /// A passenger shows up at the floor and presses the hall button towards
+    /// the destination.
+    pub async fn add_passenger(&self, destination: u8) -> Result<Passenger, FloorError> {
+        Ok(self.controller.add_passenger(self.id, destination).await?)
+    }
+
+    /// Calls the elevator group, the controller picks which car answers.
+    pub async fn hall_call(&self, direction: Direction) -> Result<Registration, FloorError> {
+        Ok(self.controller.hall_call(self.id, direction).await?)
+    }
+
+    pub fn get_outside_button(&self) -> Result<&OutSideButton, FloorError> {
+        Ok(self.controller.get_outside_button(self.id)?)
     }
 }
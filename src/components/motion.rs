@@ -0,0 +1,209 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::utils::constants::{
+    ARRIVAL_SPEED_TOLERANCE, ARRIVAL_TOLERANCE, DEFAULT_ACCELERATION, DEFAULT_FLOOR_HEIGHT,
+    DEFAULT_JERK, DEFAULT_RATED_SPEED,
+};
+
+use super::enums::Direction;
+
+/// Kinematic limits of a car and the layout of its shaft.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MotionProfile {
+    /// Height of every floor above the lowest one, in metres.
+    pub floor_heights: Vec<f64>,
+    /// Rated speed, in m/s.
+    pub rated_speed: f64,
+    /// Maximum acceleration and deceleration, in m/s².
+    pub acceleration: f64,
+    /// Maximum rate of change of the acceleration, in m/s³.
+    pub jerk: f64,
+}
+
+impl Default for MotionProfile {
+    fn default() -> Self {
+        Self {
+            floor_heights: Vec::new(),
+            rated_speed: DEFAULT_RATED_SPEED,
+            acceleration: DEFAULT_ACCELERATION,
+            jerk: DEFAULT_JERK,
+        }
+    }
+}
+
+impl MotionProfile {
+    /// Creates a profile with the default limits and the same height for every floor.
+    pub fn uniform(num_floors: u8, floor_height: f64) -> Self {
+        Self {
+            floor_heights: (0..num_floors).map(|i| i as f64 * floor_height).collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Creates a profile with the default limits and the default floor height.
+    pub fn for_floors(num_floors: u8) -> Self {
+        Self::uniform(num_floors, DEFAULT_FLOOR_HEIGHT)
+    }
+
+    /// Returns true if every limit is positive and floors are in ascending order.
+    pub fn is_valid(&self) -> bool {
+        self.rated_speed > 0.0
+            && self.acceleration > 0.0
+            && self.jerk > 0.0
+            && self.floor_heights.windows(2).all(|w| w[0] < w[1])
+    }
+
+    pub fn num_floors(&self) -> usize {
+        self.floor_heights.len()
+    }
+
+    /// Position of the floor sill in the shaft, in metres.
+    pub fn floor_position(&self, floor: u8) -> f64 {
+        self.floor_heights
+            .get(floor as usize)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// The floor closest to a position in the shaft.
+    pub fn nearest_floor(&self, position: f64) -> u8 {
+        self.floor_heights
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| (*a - position).abs().total_cmp(&(*b - position).abs()))
+            .map(|(floor, _)| floor as u8)
+            .unwrap_or_default()
+    }
+
+    /// Distance needed to come to rest, from a speed and acceleration measured
+    /// along the direction of travel.
+    ///
+    /// Deceleration ramps up to the limit at the maximum jerk, holds, then ramps
+    /// down again, which slightly overestimates short stops.
+    pub fn stopping_distance(&self, speed: f64, acceleration: f64) -> f64 {
+        if speed <= 0.0 {
+            return 0.0;
+        }
+
+        let (max_acc, jerk) = (self.acceleration, self.jerk);
+        let ramp = ((acceleration + max_acc) / jerk).max(0.0);
+        let ramp_speed = (speed + acceleration * ramp - jerk * ramp * ramp / 2.0).max(0.0);
+        let ramp_distance =
+            speed * ramp + acceleration * ramp * ramp / 2.0 - jerk * ramp * ramp * ramp / 6.0;
+
+        ramp_distance.max(0.0)
+            + ramp_speed * ramp_speed / (2.0 * max_acc)
+            + max_acc.powi(3) / (6.0 * jerk * jerk)
+    }
+
+    /// The first floor in the direction of travel the car is still able to stop at.
+    pub fn next_stoppable_floor(&self, motion: &MotionState, direction: Direction) -> u8 {
+        let sign = direction.sign();
+        let reachable = motion.position
+            + sign * self.stopping_distance(motion.velocity * sign, motion.acceleration * sign);
+
+        let floors = self.floor_heights.iter().enumerate();
+        let floor = match direction {
+            Direction::Up => floors
+                .filter(|(_, &height)| height >= reachable - ARRIVAL_TOLERANCE)
+                .map(|(floor, _)| floor)
+                .next(),
+            Direction::Down => floors
+                .filter(|(_, &height)| height <= reachable + ARRIVAL_TOLERANCE)
+                .map(|(floor, _)| floor)
+                .next_back(),
+        };
+
+        // Beyond the terminal floors, the car can only stop at the last one
+        floor.unwrap_or(match direction {
+            Direction::Up => self.num_floors().saturating_sub(1),
+            Direction::Down => 0,
+        }) as u8
+    }
+
+    /// Time needed to travel between two floors, starting and ending at rest.
+    ///
+    /// None for a zero step or an invalid profile, the car would never arrive.
+    pub fn travel_time(&self, from: u8, to: u8, step: Duration) -> Option<Duration> {
+        if step.is_zero() || !self.is_valid() {
+            return None;
+        }
+
+        let target = self.floor_position(to);
+        let mut motion = MotionState::at(self.floor_position(from));
+        let mut elapsed = Duration::ZERO;
+
+        while !motion.step(self, target, step.as_secs_f64()) {
+            elapsed += step;
+        }
+
+        Some(elapsed + step)
+    }
+}
+
+/// Continuous position of a car in the shaft.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct MotionState {
+    /// Position above the lowest floor, in metres.
+    pub position: f64,
+    /// Signed velocity, positive going up, in m/s.
+    pub velocity: f64,
+    /// Signed acceleration, positive going up, in m/s².
+    pub acceleration: f64,
+}
+
+impl MotionState {
+    pub fn at(position: f64) -> Self {
+        Self {
+            position,
+            ..Default::default()
+        }
+    }
+
+    /// Advances the car towards the target position by `dt` seconds, accelerating,
+    /// cruising or braking within the profile limits.
+    ///
+    /// Returns true once the car rests at the target.
+    pub fn step(&mut self, profile: &MotionProfile, target: f64, dt: f64) -> bool {
+        let to_go = target - self.position;
+        if to_go.abs() <= ARRIVAL_TOLERANCE && self.velocity.abs() <= ARRIVAL_SPEED_TOLERANCE {
+            *self = Self::at(target);
+            return true;
+        }
+
+        let sign = if self.velocity != 0.0 {
+            self.velocity.signum()
+        } else {
+            to_go.signum()
+        };
+        let distance = to_go * sign;
+        let speed = self.velocity * sign;
+        let mut acceleration = self.acceleration * sign;
+
+        let wanted = if distance <= 0.0 {
+            // Overshot the target, brake as hard as allowed
+            -profile.acceleration
+        } else if distance <= profile.stopping_distance(speed, acceleration) + speed * dt {
+            -(speed * speed / (2.0 * distance)).min(profile.acceleration)
+        } else if speed < profile.rated_speed {
+            profile.acceleration
+        } else {
+            0.0
+        };
+
+        let max_change = profile.jerk * dt;
+        acceleration += (wanted - acceleration).clamp(-max_change, max_change);
+
+        let new_speed = (speed + acceleration * dt).clamp(0.0, profile.rated_speed);
+        if new_speed == 0.0 || new_speed == profile.rated_speed {
+            acceleration = 0.0;
+        }
+
+        self.position += sign * (speed + new_speed) / 2.0 * dt;
+        self.velocity = sign * new_speed;
+        self.acceleration = sign * acceleration;
+
+        false
+    }
+}
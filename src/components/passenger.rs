@@ -0,0 +1,215 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use tokio::sync::Mutex;
+
+use crate::utils::{
+    atomic::{AtomicOperation, U64},
+    constants::DEFAULT_PASSENGER_WEIGHT_KG,
+};
+
+use super::enums::Direction;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum PassengerStatus {
+    /// Waiting at the origin floor.
+    Waiting,
+    /// Inside a car, on the way to the destination.
+    Riding,
+    /// Left the car at the destination.
+    Arrived,
+}
+
+/// A person travelling from one floor to another.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Passenger {
+    pub id: u64,
+    pub origin: u8,
+    pub destination: u8,
+    pub weight_kg: f64,
+    pub status: PassengerStatus,
+    /// Car the passenger boarded.
+    pub elevator_id: Option<u8>,
+    /// When the passenger showed up at the origin floor, in milliseconds.
+    pub arrived_ms: u64,
+    pub boarded_ms: Option<u64>,
+    pub alighted_ms: Option<u64>,
+}
+
+impl Passenger {
+    pub fn direction(&self) -> Direction {
+        if self.destination > self.origin {
+            Direction::Up
+        } else {
+            Direction::Down
+        }
+    }
+
+    /// Time spent waiting at the origin floor.
+    pub fn wait_ms(&self) -> Option<u64> {
+        self.boarded_ms.map(|boarded| boarded - self.arrived_ms)
+    }
+
+    /// Time from showing up at the origin floor to alighting at the destination.
+    pub fn journey_ms(&self) -> Option<u64> {
+        self.alighted_ms.map(|alighted| alighted - self.arrived_ms)
+    }
+
+    pub fn is_arrived(&self) -> bool {
+        self.status == PassengerStatus::Arrived
+    }
+}
+
+/// Every passenger in the building, shared by the controller and its cars.
+#[derive(Debug, Default)]
+pub struct PassengerRegistry {
+    next_id: U64,
+    passengers: Mutex<BTreeMap<u64, Passenger>>,
+}
+
+impl PassengerRegistry {
+    /// Adds a passenger waiting at the origin floor.
+    pub async fn add(&self, origin: u8, destination: u8, now: u64) -> Passenger {
+        let passenger = Passenger {
+            id: self.next_id.add(1),
+            origin,
+            destination,
+            weight_kg: DEFAULT_PASSENGER_WEIGHT_KG,
+            status: PassengerStatus::Waiting,
+            elevator_id: None,
+            arrived_ms: now,
+            boarded_ms: None,
+            alighted_ms: None,
+        };
+        self.passengers
+            .lock()
+            .await
+            .insert(passenger.id, passenger.clone());
+        passenger
+    }
+
+    pub async fn get(&self, id: u64) -> Option<Passenger> {
+        self.passengers.lock().await.get(&id).cloned()
+    }
+
+    /// Lists every passenger, in id order.
+    pub async fn list(&self) -> Vec<Passenger> {
+        self.passengers.lock().await.values().cloned().collect()
+    }
+
+    /// Returns true if every passenger reached their destination.
+    pub async fn is_all_arrived(&self) -> bool {
+        self.passengers
+            .lock()
+            .await
+            .values()
+            .all(Passenger::is_arrived)
+    }
+
+    /// Returns true if someone waits at the floor to travel the direction.
+    pub async fn is_waiting(&self, floor: u8, direction: Direction) -> bool {
+        self.passengers.lock().await.values().any(|p| {
+            p.status == PassengerStatus::Waiting && p.origin == floor && p.direction() == direction
+        })
+    }
+
+    /// Removes the passengers who reached their destination and returns them.
+    pub async fn drain_arrived(&self) -> Vec<Passenger> {
+        let mut passengers = self.passengers.lock().await;
+        let arrived = passengers
+            .values()
+            .filter(|p| p.is_arrived())
+            .map(|p| p.id)
+            .collect::<Vec<u64>>();
+        arrived
+            .into_iter()
+            .filter_map(|id| passengers.remove(&id))
+            .collect()
+    }
+
+    /// Forgets the passengers who reached their destination first, only the
+    /// latest ones are kept for the statistics.
+    pub async fn prune_arrived(&self, keep: usize) {
+        let mut passengers = self.passengers.lock().await;
+        let arrived = passengers
+            .values()
+            .filter(|p| p.is_arrived())
+            .map(|p| p.id)
+            .collect::<Vec<u64>>();
+        for id in &arrived[..arrived.len().saturating_sub(keep)] {
+            passengers.remove(id);
+        }
+    }
+
+    /// Lets the riders of a car out at their destination, returns who alighted.
+    pub async fn alight(&self, elevator_id: u8, floor: u8, now: u64) -> Vec<Passenger> {
+        self.leave(elevator_id, now, |passenger| passenger.destination == floor)
+            .await
+    }
+
+    /// Lets every rider out of a recalled car, their journey ends at the recall
+    /// floor, returns who alighted.
+    pub async fn evacuate(&self, elevator_id: u8, now: u64) -> Vec<Passenger> {
+        self.leave(elevator_id, now, |_| true).await
+    }
+
+    async fn leave(
+        &self,
+        elevator_id: u8,
+        now: u64,
+        leaves: impl Fn(&Passenger) -> bool,
+    ) -> Vec<Passenger> {
+        let mut alighted = Vec::new();
+
+        for passenger in self.passengers.lock().await.values_mut() {
+            if passenger.status == PassengerStatus::Riding
+                && passenger.elevator_id == Some(elevator_id)
+                && leaves(passenger)
+            {
+                passenger.status = PassengerStatus::Arrived;
+                passenger.alighted_ms = Some(now);
+                alighted.push(passenger.clone());
+            }
+        }
+
+        alighted
+    }
+
+    /// Lets waiting passengers into a car standing at a floor with open doors,
+    /// returns who boarded.
+    ///
+    /// Passengers board when the car heads their way, or has nothing left to
+    /// do, as long as there is room left for them.
+    pub async fn board(
+        &self,
+        elevator_id: u8,
+        floor: u8,
+        heading: Option<Direction>,
+        room: (u8, f64),
+        now: u64,
+    ) -> Vec<Passenger> {
+        let (mut persons, mut load_kg) = room;
+        let mut boarded = Vec::new();
+
+        for passenger in self.passengers.lock().await.values_mut() {
+            if persons == 0 {
+                break;
+            }
+            if passenger.status != PassengerStatus::Waiting
+                || passenger.origin != floor
+                || heading.is_some_and(|heading| heading != passenger.direction())
+                || passenger.weight_kg > load_kg
+            {
+                continue;
+            }
+
+            passenger.status = PassengerStatus::Riding;
+            passenger.elevator_id = Some(elevator_id);
+            passenger.boarded_ms = Some(now);
+            persons -= 1;
+            load_kg -= passenger.weight_kg;
+            boarded.push(passenger.clone());
+        }
+
+        boarded
+    }
+}
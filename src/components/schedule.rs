@@ -0,0 +1,191 @@
+use serde::Serialize;
+use std::collections::BTreeSet;
+
+use super::enums::Direction;
+
+/// Where a pending stop was registered from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub enum StopOrigin {
+    /// Inside button of the car.
+    Car,
+    /// Up button of the hall.
+    HallUp,
+    /// Down button of the hall.
+    HallDown,
+}
+
+impl StopOrigin {
+    pub fn hall(direction: Direction) -> Self {
+        match direction {
+            Direction::Up => Self::HallUp,
+            Direction::Down => Self::HallDown,
+        }
+    }
+
+    /// Direction of the hall button, none for the inside button.
+    pub fn direction(self) -> Option<Direction> {
+        match self {
+            Self::Car => None,
+            Self::HallUp => Some(Direction::Up),
+            Self::HallDown => Some(Direction::Down),
+        }
+    }
+}
+
+/// Pending stops of a car, keyed by floor and origin.
+///
+/// Hall calls are served in their own direction, car calls are served in
+/// whatever direction the car passes by. Registering the same stop twice is
+/// merged into a single stop.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct StopSet {
+    pub car: BTreeSet<u8>,
+    pub up: BTreeSet<u8>,
+    pub down: BTreeSet<u8>,
+}
+
+impl StopSet {
+    pub fn is_empty(&self) -> bool {
+        self.car.is_empty() && self.up.is_empty() && self.down.is_empty()
+    }
+
+    pub fn contains(&self, floor: u8) -> bool {
+        self.car.contains(&floor) || self.up.contains(&floor) || self.down.contains(&floor)
+    }
+
+    fn floors(&self, origin: StopOrigin) -> &BTreeSet<u8> {
+        match origin {
+            StopOrigin::Car => &self.car,
+            StopOrigin::HallUp => &self.up,
+            StopOrigin::HallDown => &self.down,
+        }
+    }
+
+    fn floors_mut(&mut self, origin: StopOrigin) -> &mut BTreeSet<u8> {
+        match origin {
+            StopOrigin::Car => &mut self.car,
+            StopOrigin::HallUp => &mut self.up,
+            StopOrigin::HallDown => &mut self.down,
+        }
+    }
+
+    /// Keeps the car calls only, for a car passing hall calls by.
+    pub fn car_calls(&self) -> Self {
+        Self {
+            car: self.car.clone(),
+            ..Default::default()
+        }
+    }
+
+    pub fn is_pending(&self, floor: u8, origin: StopOrigin) -> bool {
+        self.floors(origin).contains(&floor)
+    }
+
+    /// Adds a stop, returns false if it was already pending.
+    pub fn insert(&mut self, floor: u8, origin: StopOrigin) -> bool {
+        self.floors_mut(origin).insert(floor)
+    }
+
+    /// Removes a stop, returns false if it was not pending.
+    pub fn remove(&mut self, floor: u8, origin: StopOrigin) -> bool {
+        self.floors_mut(origin).remove(&floor)
+    }
+
+    /// Removes the stops answered by a car stopping at the floor, heading the
+    /// direction. Returns true if the hall call of that direction was pending.
+    pub fn serve(&mut self, floor: u8, direction: Direction) -> bool {
+        self.car.remove(&floor);
+        self.remove(floor, StopOrigin::hall(direction))
+    }
+
+    /// Picks the next stop following the collective (LOOK) strategy.
+    ///
+    /// The car keeps its heading while there are stops ahead, serving car calls and
+    /// hall calls of its own direction on the way and turning at the furthest
+    /// opposite call, then reverses. Returns the target floor and the direction it
+    /// is served in.
+    pub fn next_stop(&self, floor: u8, heading: Option<Direction>) -> Option<(u8, Direction)> {
+        let heading = match heading {
+            Some(heading) => heading,
+            None => {
+                // Idle car, head to the closest stop
+                let nearest = self
+                    .car
+                    .iter()
+                    .chain(self.up.iter())
+                    .chain(self.down.iter())
+                    .min_by_key(|stop| stop.abs_diff(floor))?;
+                if *nearest < floor {
+                    Direction::Down
+                } else {
+                    Direction::Up
+                }
+            }
+        };
+
+        match heading {
+            Direction::Up => self.look_up(floor).or_else(|| self.look_down(floor)),
+            Direction::Down => self.look_down(floor).or_else(|| self.look_up(floor)),
+        }
+    }
+
+    fn look_up(&self, floor: u8) -> Option<(u8, Direction)> {
+        if self.car.contains(&floor) || self.up.contains(&floor) {
+            return Some((floor, Direction::Up));
+        }
+
+        let above = floor.saturating_add(1)..;
+        let car = self.car.range(above.clone()).next();
+        let up = self.up.range(above).next();
+        if let Some(&stop) = car.into_iter().chain(up).min() {
+            return Some((stop, Direction::Up));
+        }
+
+        // Travel up to the highest down call, where the car turns around
+        match self.down.range(floor..).next_back() {
+            Some(&stop) if stop > floor => Some((stop, Direction::Up)),
+            _ => None,
+        }
+    }
+
+    fn look_down(&self, floor: u8) -> Option<(u8, Direction)> {
+        if self.car.contains(&floor) || self.down.contains(&floor) {
+            return Some((floor, Direction::Down));
+        }
+
+        let car = self.car.range(..floor).next_back();
+        let down = self.down.range(..floor).next_back();
+        if let Some(&stop) = car.into_iter().chain(down).max() {
+            return Some((stop, Direction::Down));
+        }
+
+        // Travel down to the lowest up call, where the car turns around
+        self.up
+            .range(..floor)
+            .next()
+            .map(|&stop| (stop, Direction::Down))
+    }
+
+    /// Lists the floors the car will stop at, in order, if no new stop is added.
+    pub fn route(&self, mut floor: u8, mut heading: Option<Direction>) -> Vec<u8> {
+        let mut stops = self.clone();
+        let mut route = Vec::new();
+
+        while let Some((stop, direction)) = stops.next_stop(floor, heading) {
+            if stop == floor {
+                stops.serve(stop, direction);
+                route.push(stop);
+                heading = Some(direction);
+            } else {
+                heading = Some(if stop > floor {
+                    Direction::Up
+                } else {
+                    Direction::Down
+                });
+                floor = stop;
+            }
+        }
+
+        route
+    }
+}
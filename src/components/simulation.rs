@@ -0,0 +1,180 @@
+use serde::{Deserialize, Serialize};
+use std::{sync::Arc, time::Duration};
+
+use crate::{
+    utils::{
+        atomic::AtomicValue,
+        clock::Clock,
+        constants::{DEFAULT_SIMULATION_TIME_LIMIT_MS, ELEVATOR_SELF_CHECK_MS},
+    },
+    ElevatorController,
+};
+
+use super::{enums::Direction, error::SimulationError, model::Building, passenger::Passenger};
+
+/// A passenger showing up at a floor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Arrival {
+    /// Time since the start of the simulation, in milliseconds.
+    pub at_ms: u64,
+    pub origin: u8,
+    pub destination: u8,
+}
+
+impl Arrival {
+    pub fn direction(&self) -> Direction {
+        if self.destination > self.origin {
+            Direction::Up
+        } else {
+            Direction::Down
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SimulationReport {
+    /// Simulated time the run took, in milliseconds.
+    pub elapsed_ms: u64,
+    /// Every passenger who showed up, in the order of the arrival stream, timed
+    /// by the simulation clock.
+    pub passengers: Vec<Passenger>,
+}
+
+impl SimulationReport {
+    /// Number of passengers who reached their destination.
+    pub fn completed(&self) -> usize {
+        self.passengers.iter().filter(|p| p.is_arrived()).count()
+    }
+}
+
+/// Headless discrete-event simulation of a building.
+///
+/// Time jumps from one event to the next: a passenger arrival, the doors of a
+/// car finishing their motion, or a travelling car passing a floor or
+/// arriving. Only a car recovering from a fault is followed in self check steps.
+#[derive(Debug)]
+pub struct Simulation {
+    building: Arc<Building>,
+    time_limit_ms: u64,
+}
+
+impl Simulation {
+    /// Puts the controller on a manual clock and wraps it in a building.
+    pub fn new(controller: ElevatorController) -> Self {
+        let num_floors = controller.outside_buttons.len() as u8;
+        let controller = Arc::new(controller.with_clock(Clock::manual()));
+
+        Self {
+            building: Arc::new(Building::new(num_floors, controller)),
+            time_limit_ms: DEFAULT_SIMULATION_TIME_LIMIT_MS,
+        }
+    }
+
+    /// Stops the run after this much simulated time, even if passengers are left.
+    pub fn with_time_limit(mut self, limit: Duration) -> Self {
+        self.time_limit_ms = limit.as_millis() as u64;
+        self
+    }
+
+    pub fn building(&self) -> &Arc<Building> {
+        &self.building
+    }
+
+    /// Runs the arrival stream until every passenger reached their destination.
+    pub async fn run(
+        &self,
+        arrivals: impl IntoIterator<Item = Arrival>,
+    ) -> Result<SimulationReport, SimulationError> {
+        let num_floors = self.building.floors.len() as u8;
+        let arrivals = arrivals.into_iter().collect::<Vec<Arrival>>();
+        if let Some(arrival) = arrivals.iter().find(|a| {
+            a.origin == a.destination || a.origin >= num_floors || a.destination >= num_floors
+        }) {
+            return Err(SimulationError::InvalidArrival {
+                origin: arrival.origin,
+                destination: arrival.destination,
+            });
+        }
+
+        let mut order = (0..arrivals.len()).collect::<Vec<usize>>();
+        order.sort_by_key(|&id| arrivals[id].at_ms);
+
+        let controller = &self.building.controller;
+        let start = controller.clock().now_ms();
+        let mut next_arrival = order.into_iter().peekable();
+        let mut passenger_ids = vec![None; arrivals.len()];
+
+        loop {
+            let now = controller.clock().now_ms() - start;
+            while let Some(id) = next_arrival.next_if(|&id| arrivals[id].at_ms <= now) {
+                let Arrival {
+                    origin,
+                    destination,
+                    ..
+                } = arrivals[id];
+                match controller.add_passenger(origin, destination).await {
+                    Ok(passenger) => passenger_ids[id] = Some(passenger.id),
+                    Err(err) => warn!("[simulation]: passenger {id} cannot show up: {err}"),
+                }
+            }
+
+            if next_arrival.peek().is_none() && controller.passengers.is_all_arrived().await {
+                break;
+            }
+            if now >= self.time_limit_ms {
+                warn!("[simulation]: time limit reached with passengers left");
+                break;
+            }
+
+            let arrival_in = next_arrival
+                .peek()
+                .map(|&id| arrivals[id].at_ms.saturating_sub(now));
+            let Some(step) = self.next_event_in(arrival_in).await else {
+                warn!("[simulation]: nothing left to happen, passengers are stuck");
+                break;
+            };
+            controller.step(Duration::from_millis(step.max(1))).await?;
+        }
+
+        let mut passengers = Vec::with_capacity(arrivals.len());
+        for id in passenger_ids.into_iter().flatten() {
+            if let Some(passenger) = controller.passengers.get(id).await {
+                passengers.push(passenger);
+            }
+        }
+
+        Ok(SimulationReport {
+            elapsed_ms: controller.clock().now_ms() - start,
+            passengers,
+        })
+    }
+
+    /// Time until something happens, in milliseconds, none if nothing ever will.
+    async fn next_event_in(&self, arrival_in: Option<u64>) -> Option<u64> {
+        let controller = &self.building.controller;
+        let now = controller.clock().now_ms();
+        let mut next = arrival_in;
+
+        for elevator in controller.elevators.values() {
+            let event_in = if elevator.is_faulted() {
+                None
+            } else if elevator.recovering.val() {
+                Some(ELEVATOR_SELF_CHECK_MS)
+            } else if !elevator.is_idle() {
+                elevator.motion_event_in().or(Some(ELEVATOR_SELF_CHECK_MS))
+            } else if let Some(deadline) = elevator.door_deadline_ms() {
+                Some(deadline.saturating_sub(now))
+            } else if elevator.has_stops().await {
+                Some(0)
+            } else {
+                None
+            };
+            next = match (next, event_in) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            };
+        }
+
+        next
+    }
+}
@@ -0,0 +1,178 @@
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::utils::constants::TRAFFIC_RATE_PERIOD_MS;
+
+use super::{model::Building, passenger::Passenger, simulation::Arrival};
+
+/// Standard traffic patterns of an office building.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TrafficPattern {
+    /// Morning, people arrive at the lobby and go up.
+    UpPeak,
+    /// Evening, people leave for the lobby.
+    DownPeak,
+    /// Lunch time, as many people leave as come back.
+    Lunch,
+    /// People moving between the upper floors.
+    Interfloor,
+}
+
+impl TrafficPattern {
+    /// Share of passengers coming from the lobby and of passengers going to it,
+    /// the rest travels between the upper floors.
+    pub fn mix(&self) -> (f64, f64) {
+        match self {
+            Self::UpPeak => (0.85, 0.1),
+            Self::DownPeak => (0.1, 0.85),
+            Self::Lunch => (0.45, 0.45),
+            Self::Interfloor => (0.0, 0.0),
+        }
+    }
+}
+
+/// Seeded stream of passenger arrivals following a traffic pattern.
+///
+/// Arrivals follow a Poisson process, the same seed always gives the same stream.
+#[derive(Debug, Clone)]
+pub struct TrafficGenerator {
+    pattern: TrafficPattern,
+    num_floors: u8,
+    lobby: u8,
+    /// Mean number of passengers showing up every 5 minutes.
+    arrival_rate: f64,
+    at_ms: f64,
+    rng: ChaCha8Rng,
+    /// First arrival past the part of the stream listed so far.
+    pending: Option<Arrival>,
+    /// End of the part of the stream listed so far, in milliseconds.
+    listed_ms: u64,
+    /// Clock time the stream started at when it was first fed, in milliseconds.
+    fed_from_ms: Option<u64>,
+}
+
+impl TrafficGenerator {
+    pub fn new(pattern: TrafficPattern, num_floors: u8, arrival_rate: f64, seed: u64) -> Self {
+        Self {
+            pattern,
+            num_floors,
+            lobby: 0,
+            arrival_rate,
+            at_ms: 0.0,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            pending: None,
+            listed_ms: 0,
+            fed_from_ms: None,
+        }
+    }
+
+    /// Moves the main entrance of the building, the lowest floor by default.
+    pub fn with_lobby(mut self, lobby: u8) -> Self {
+        self.lobby = lobby;
+        self
+    }
+
+    pub fn pattern(&self) -> TrafficPattern {
+        self.pattern
+    }
+
+    /// Lists the arrivals of the next window of the stream, each window starts
+    /// where the previous one ended.
+    pub fn generate(&mut self, duration: Duration) -> Vec<Arrival> {
+        let until = self.listed_ms + duration.as_millis() as u64;
+        self.listed_ms = until;
+        let mut arrivals = Vec::new();
+        while let Some(arrival) = self.next() {
+            if arrival.at_ms >= until {
+                self.pending = Some(arrival);
+                break;
+            }
+            arrivals.push(arrival);
+        }
+        arrivals
+    }
+
+    /// Feeds the stream into a building for a while, each passenger presses the
+    /// hall button of their origin floor as they show up.
+    ///
+    /// Consecutive calls feed consecutive windows, the stream starts at the
+    /// time of the first call. A building on a manual clock is advanced to
+    /// every arrival, otherwise the arrivals happen in real time.
+    pub async fn feed(&mut self, building: &Building, duration: Duration) -> Vec<Passenger> {
+        let controller = &building.controller;
+        let clock = controller.clock();
+        let start = *self.fed_from_ms.get_or_insert_with(|| clock.now_ms());
+        let mut passengers = Vec::new();
+
+        for arrival in self.generate(duration) {
+            let wait =
+                Duration::from_millis((start + arrival.at_ms).saturating_sub(clock.now_ms()));
+            if controller.advance(wait).await.is_err() {
+                // A real-time clock cannot be advanced, the arrivals happen in real time
+                tokio::time::sleep(wait).await;
+            }
+
+            match building
+                .add_passenger(arrival.origin, arrival.destination)
+                .await
+            {
+                Ok(passenger) => passengers.push(passenger),
+                Err(err) => warn!("[traffic]: passenger cannot show up: {err}"),
+            }
+        }
+
+        passengers
+    }
+
+    /// Any floor but the lobby, uniformly.
+    fn upper_floor(&mut self) -> u8 {
+        let floor = self.rng.gen_range(0..self.num_floors - 1);
+        if floor >= self.lobby {
+            floor + 1
+        } else {
+            floor
+        }
+    }
+}
+
+impl Iterator for TrafficGenerator {
+    type Item = Arrival;
+
+    fn next(&mut self) -> Option<Arrival> {
+        if let Some(arrival) = self.pending.take() {
+            return Some(arrival);
+        }
+        if self.num_floors < 2 || self.lobby >= self.num_floors || self.arrival_rate <= 0.0 {
+            return None;
+        }
+
+        let mean_interval = TRAFFIC_RATE_PERIOD_MS as f64 / self.arrival_rate;
+        self.at_ms -= mean_interval * (1.0 - self.rng.gen::<f64>()).ln();
+
+        let (incoming, outgoing) = self.pattern.mix();
+        let kind = self.rng.gen::<f64>();
+        let (origin, destination) = if kind < incoming {
+            (self.lobby, self.upper_floor())
+        } else if kind < incoming + outgoing {
+            (self.upper_floor(), self.lobby)
+        } else if self.num_floors < 3 {
+            // No two upper floors to travel between
+            (self.lobby, self.upper_floor())
+        } else {
+            let origin = self.upper_floor();
+            let mut destination = self.upper_floor();
+            while destination == origin {
+                destination = self.upper_floor();
+            }
+            (origin, destination)
+        };
+
+        Some(Arrival {
+            at_ms: self.at_ms as u64,
+            origin,
+            destination,
+        })
+    }
+}
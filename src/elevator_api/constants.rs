@@ -2,3 +2,13 @@ pub const NUM_FLOORS: u8 = 5;
 pub const NUM_ELEVATORS: u8 = 3;
 pub const INIT_FLOOR: u8 = 2;
 pub const DISPLAY_OFFSET: i16 = -2;
+/// Environment variable holding the token that enables fault injection.
+pub const FAULT_TOKEN_ENV: &str = "ELEVATOR_FAULT_TOKEN";
+pub const FAULT_TOKEN_HEADER: &str = "x-fault-token";
+/// Environment variable holding the token that enables the admin commands.
+pub const ADMIN_TOKEN_ENV: &str = "ELEVATOR_ADMIN_TOKEN";
+pub const ADMIN_TOKEN_HEADER: &str = "x-admin-token";
+/// How often the building state is compared while a car moves, for WebSocket clients.
+pub const WS_MOTION_INTERVAL_MS: u64 = 100;
+/// State changes kept for WebSocket clients falling behind.
+pub const WS_CHANNEL_CAPACITY: usize = 64;
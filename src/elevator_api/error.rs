@@ -0,0 +1,129 @@
+use axum::{
+    extract::rejection::{JsonRejection, PathRejection},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use elevator_core::components::error::{
+    BuildingError, ElevatorControllerError, ElevatorError, FloorError,
+};
+
+use super::utils::json_resp;
+
+/// Failure of a v2 request, answered with its status, a machine-readable code
+/// and the values the error is about.
+#[derive(Debug)]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub code: &'static str,
+    pub message: String,
+    pub details: Value,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, code: &'static str, message: impl ToString) -> Self {
+        Self {
+            status,
+            code,
+            message: message.to_string(),
+            details: json!({}),
+        }
+    }
+
+    /// Answers with a core error, its fields but the code become the details.
+    fn coded<E>(status: StatusCode, code: &'static str, err: E) -> Self
+    where
+        E: Serialize + std::fmt::Display,
+    {
+        let mut details = serde_json::to_value(&err).unwrap_or_else(|_| json!({}));
+        if let Some(fields) = details.as_object_mut() {
+            fields.remove("code");
+        }
+
+        Self {
+            details,
+            ..Self::new(status, code, err)
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = json!({
+            "error": { "code": self.code, "message": self.message, "details": self.details }
+        });
+        (self.status, json_resp(&body)).into_response()
+    }
+}
+
+impl From<ElevatorError> for ApiError {
+    fn from(err: ElevatorError) -> Self {
+        let status = match err {
+            ElevatorError::RequestError => StatusCode::BAD_REQUEST,
+            ElevatorError::SignalNotExists => StatusCode::SERVICE_UNAVAILABLE,
+            ElevatorError::NothingToCancel { .. } => StatusCode::NOT_FOUND,
+            ElevatorError::AlreadyMaxFloor { .. }
+            | ElevatorError::AlreadyMinFloor { .. }
+            | ElevatorError::FloorButtonNotExists { .. }
+            | ElevatorError::InvalidMotionProfile => StatusCode::UNPROCESSABLE_ENTITY,
+            ElevatorError::CannotControlDoor
+            | ElevatorError::AlreadyStarted
+            | ElevatorError::ManualClock
+            | ElevatorError::CannotChangeMotionProfile
+            | ElevatorError::HallCallAssigned { .. }
+            | ElevatorError::OperatingModeRejected { .. }
+            | ElevatorError::Faulted { .. }
+            | ElevatorError::NotFaulted => StatusCode::CONFLICT,
+        };
+        Self::coded(status, err.code(), err)
+    }
+}
+
+impl From<ElevatorControllerError> for ApiError {
+    fn from(err: ElevatorControllerError) -> Self {
+        let status = match err {
+            ElevatorControllerError::GetElevatorError { .. } => StatusCode::NOT_FOUND,
+            ElevatorControllerError::NoElevatorAvailable
+            | ElevatorControllerError::RealtimeClock => StatusCode::CONFLICT,
+            ElevatorControllerError::InvalidPassenger { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            ElevatorControllerError::ElevatorError(err) => return err.into(),
+        };
+        Self::coded(status, err.code(), err)
+    }
+}
+
+impl From<FloorError> for ApiError {
+    fn from(err: FloorError) -> Self {
+        match err {
+            FloorError::GetPanelError { .. } => Self::coded(StatusCode::NOT_FOUND, err.code(), err),
+            FloorError::ElevatorError(err) => err.into(),
+            FloorError::ElevatorControllerError(err) => err.into(),
+        }
+    }
+}
+
+impl From<BuildingError> for ApiError {
+    fn from(err: BuildingError) -> Self {
+        match err {
+            BuildingError::GetFloorError { .. } => {
+                Self::coded(StatusCode::NOT_FOUND, err.code(), err)
+            }
+            BuildingError::FloorError(err) => err.into(),
+            BuildingError::ElevatorControllerError(err) => err.into(),
+        }
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> Self {
+        Self::new(rejection.status(), "invalid_body", rejection.body_text())
+    }
+}
+
+impl From<PathRejection> for ApiError {
+    fn from(rejection: PathRejection) -> Self {
+        Self::new(rejection.status(), "invalid_path", rejection.body_text())
+    }
+}
@@ -1,4 +1,8 @@
-use serde::Deserialize;
+use elevator_core::components::{
+    enums::{Direction, OperatingMode},
+    fault::FaultCode,
+};
+use serde::{Deserialize, Serialize};
 
 #[derive(Deserialize)]
 pub struct DoorControlQuery {
@@ -7,9 +11,83 @@ pub struct DoorControlQuery {
 }
 
 #[derive(Deserialize)]
-pub struct RequestQuery {
+pub struct DoorObstructionQuery {
+    pub elevator_id: u8,
+    pub obstructed: bool,
+}
+
+#[derive(Deserialize)]
+pub struct ElevatorQuery {
     pub elevator_id: u8,
+}
+
+#[derive(Deserialize)]
+pub struct FireAlarmQuery {
+    pub active: bool,
+}
+
+#[derive(Deserialize)]
+pub struct FirefighterQuery {
+    pub elevator_id: u8,
+    pub on: bool,
+}
+
+#[derive(Deserialize)]
+pub struct OperatingModeQuery {
+    pub elevator_id: u8,
+    pub mode: OperatingMode,
+}
+
+#[derive(Deserialize)]
+pub struct FaultQuery {
+    pub elevator_id: u8,
+    pub fault: FaultCode,
+}
+
+#[derive(Deserialize)]
+pub struct RequestQuery {
+    pub elevator_id: Option<u8>,
     pub floor: u8,
     pub direction: Option<String>,
     pub cancel: Option<bool>,
 }
+
+#[derive(Deserialize)]
+pub struct CarCallBody {
+    pub floor: u8,
+}
+
+/// Hall button direction as spelled in v2 bodies and paths, snake_case like
+/// the rest of the v2 API.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HallDirection {
+    Up,
+    Down,
+}
+
+impl From<HallDirection> for Direction {
+    fn from(direction: HallDirection) -> Self {
+        match direction {
+            HallDirection::Up => Direction::Up,
+            HallDirection::Down => Direction::Down,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct HallCallBody {
+    pub direction: HallDirection,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DoorCommand {
+    Open,
+    Close,
+}
+
+#[derive(Deserialize)]
+pub struct DoorBody {
+    pub command: DoorCommand,
+}
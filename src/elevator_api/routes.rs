@@ -1,6 +1,6 @@
 use axum::{
     extract::Query,
-    http::{header, HeaderValue},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
     response::IntoResponse,
     Extension,
 };
@@ -14,8 +14,12 @@ use elevator_core::{
 use std::sync::Arc;
 
 use super::{
-    extrators::{DoorControlQuery, RequestQuery},
-    utils::{fail, json_resp, map_json_result},
+    error::ApiError,
+    extrators::{
+        DoorControlQuery, DoorObstructionQuery, ElevatorQuery, FaultQuery, FireAlarmQuery,
+        FirefighterQuery, OperatingModeQuery, RequestQuery,
+    },
+    utils::{check_admin_token, check_fault_token, fail, json_resp, map_json_result, success},
 };
 
 pub async fn root() -> impl IntoResponse {
@@ -51,12 +55,39 @@ pub async fn request(
     };
 
     if let Some(direction) = direction {
+        let Some(elevator_id) = elevator_id else {
+            // Hall call without a car, let the controller dispatch it
+            let result = if cancel.unwrap_or(false) {
+                building.controller.cancel_hall_call(floor, direction).await
+            } else {
+                building.controller.hall_call(floor, direction).await
+            };
+            return json_resp(&map_json_result(result));
+        };
+
         match building.get_eleavtor(elevator_id) {
-            Ok(e) => return json_resp(&map_json_result(e.call(floor, direction).await)),
+            Ok(e) => {
+                return json_resp(&map_json_result(
+                    e.submit(ElevatorAction::Call {
+                        floor,
+                        direction,
+                        cancel: cancel.unwrap_or(false),
+                    })
+                    .await,
+                ))
+            }
             Err(err) => return json_resp(&fail(err)),
         };
     }
 
+    let Some(elevator_id) = elevator_id else {
+        return json_resp(&fail(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "elevator_id_required",
+            "elevator_id is required",
+        )));
+    };
+
     json_resp(&map_json_result(
         building
             .controller
@@ -84,3 +115,118 @@ pub async fn door_control(
             .door_control(elevator_id, DoorStatus::from(status)),
     ))
 }
+
+pub async fn door_release(
+    Extension(building): Extension<Arc<Building>>,
+    Query(ElevatorQuery { elevator_id }): Query<ElevatorQuery>,
+) -> impl IntoResponse {
+    json_resp(&map_json_result(
+        building.controller.door_release(elevator_id),
+    ))
+}
+
+pub async fn door_obstruction(
+    Extension(building): Extension<Arc<Building>>,
+    Query(DoorObstructionQuery {
+        elevator_id,
+        obstructed,
+    }): Query<DoorObstructionQuery>,
+) -> impl IntoResponse {
+    json_resp(&map_json_result(
+        building
+            .controller
+            .door_obstruction(elevator_id, obstructed),
+    ))
+}
+
+pub async fn stats(Extension(building): Extension<Arc<Building>>) -> impl IntoResponse {
+    json_resp(&success(building.controller.stats().await))
+}
+
+pub async fn fire_alarm(
+    Extension(building): Extension<Arc<Building>>,
+    headers: HeaderMap,
+    Query(FireAlarmQuery { active }): Query<FireAlarmQuery>,
+) -> impl IntoResponse {
+    if let Err(err) = check_admin_token(&headers) {
+        return (err.status, json_resp(&fail(err))).into_response();
+    }
+
+    json_resp(&map_json_result(building.fire_alarm(active).await))
+}
+
+pub async fn firefighter(
+    Extension(building): Extension<Arc<Building>>,
+    headers: HeaderMap,
+    Query(FirefighterQuery { elevator_id, on }): Query<FirefighterQuery>,
+) -> impl IntoResponse {
+    if let Err(err) = check_admin_token(&headers) {
+        return (err.status, json_resp(&fail(err))).into_response();
+    }
+
+    json_resp(&map_json_result(
+        building
+            .controller
+            .firefighter_service(elevator_id, on)
+            .await,
+    ))
+}
+
+pub async fn operating_mode(
+    Extension(building): Extension<Arc<Building>>,
+    headers: HeaderMap,
+    Query(OperatingModeQuery { elevator_id, mode }): Query<OperatingModeQuery>,
+) -> impl IntoResponse {
+    if let Err(err) = check_admin_token(&headers) {
+        return (err.status, json_resp(&fail(err))).into_response();
+    }
+
+    json_resp(&map_json_result(
+        building
+            .controller
+            .set_operating_mode(elevator_id, mode)
+            .await,
+    ))
+}
+
+pub async fn emergency_stop(
+    Extension(building): Extension<Arc<Building>>,
+    headers: HeaderMap,
+    Query(ElevatorQuery { elevator_id }): Query<ElevatorQuery>,
+) -> impl IntoResponse {
+    if let Err(err) = check_admin_token(&headers) {
+        return (err.status, json_resp(&fail(err))).into_response();
+    }
+
+    json_resp(&map_json_result(
+        building.controller.emergency_stop(elevator_id).await,
+    ))
+}
+
+pub async fn reset_fault(
+    Extension(building): Extension<Arc<Building>>,
+    headers: HeaderMap,
+    Query(ElevatorQuery { elevator_id }): Query<ElevatorQuery>,
+) -> impl IntoResponse {
+    if let Err(err) = check_admin_token(&headers) {
+        return (err.status, json_resp(&fail(err))).into_response();
+    }
+
+    json_resp(&map_json_result(
+        building.controller.reset_fault(elevator_id),
+    ))
+}
+
+pub async fn inject_fault(
+    Extension(building): Extension<Arc<Building>>,
+    headers: HeaderMap,
+    Query(FaultQuery { elevator_id, fault }): Query<FaultQuery>,
+) -> impl IntoResponse {
+    if let Err(err) = check_fault_token(&headers) {
+        return (err.status, json_resp(&fail(err))).into_response();
+    }
+
+    json_resp(&map_json_result(
+        building.controller.inject_fault(elevator_id, fault).await,
+    ))
+}
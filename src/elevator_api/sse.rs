@@ -0,0 +1,92 @@
+use axum::{
+    http::HeaderMap,
+    response::sse::{Event, KeepAlive, Sse},
+    Extension,
+};
+use futures_util::{stream, Stream};
+use std::{convert::Infallible, sync::Arc, vec};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use elevator_core::components::{
+    event::{EventBus, EventRecord},
+    model::Building,
+};
+
+/// Streams the events of the cars, a client reconnecting with `Last-Event-ID`
+/// first receives the kept events it missed.
+pub async fn events(
+    Extension(building): Extension<Arc<Building>>,
+    headers: HeaderMap,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let last_id = headers
+        .get("last-event-id")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse().ok());
+
+    let events = stream::unfold(
+        EventStream::new(building.controller.events.clone(), last_id),
+        |mut events| async move {
+            let record = events.next().await?;
+            Some((Ok(sse_event(&record)), events))
+        },
+    );
+
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
+
+/// Events still to send to a client, following the id of the last one sent.
+pub struct EventStream {
+    bus: Arc<EventBus>,
+    replay: vec::IntoIter<EventRecord>,
+    receiver: broadcast::Receiver<EventRecord>,
+    last_id: u64,
+}
+
+impl EventStream {
+    /// Starts with the kept events after `last_id` when the client had one,
+    /// otherwise with the events published from now on.
+    pub fn new(bus: Arc<EventBus>, last_id: Option<u64>) -> Self {
+        let (replay, receiver) = match last_id {
+            Some(id) => bus.resume(id),
+            None => (Vec::new(), bus.subscribe()),
+        };
+        Self {
+            bus,
+            replay: replay.into_iter(),
+            receiver,
+            last_id: last_id.unwrap_or_default(),
+        }
+    }
+
+    /// Next event to send, none once the bus is gone.
+    ///
+    /// A client falling behind catches up from the kept events, the ones it
+    /// already got are skipped.
+    pub async fn next(&mut self) -> Option<EventRecord> {
+        loop {
+            let record = match self.replay.next() {
+                Some(record) => record,
+                None => match self.receiver.recv().await {
+                    Ok(record) => record,
+                    Err(RecvError::Lagged(_)) => {
+                        self.replay = self.bus.since(self.last_id).into_iter();
+                        continue;
+                    }
+                    Err(RecvError::Closed) => return None,
+                },
+            };
+            if record.id > self.last_id {
+                self.last_id = record.id;
+                return Some(record);
+            }
+        }
+    }
+}
+
+fn sse_event(record: &EventRecord) -> Event {
+    Event::default()
+        .id(record.id.to_string())
+        .event(record.event.name())
+        .json_data(record)
+        .unwrap()
+}
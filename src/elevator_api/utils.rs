@@ -1,5 +1,5 @@
 use axum::{
-    http::{header, HeaderValue},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
     response::{IntoResponse, Response},
     Extension,
 };
@@ -9,6 +9,11 @@ use std::sync::Arc;
 
 use elevator_core::components::model::Building;
 
+use super::{
+    constants::{ADMIN_TOKEN_ENV, ADMIN_TOKEN_HEADER, FAULT_TOKEN_ENV, FAULT_TOKEN_HEADER},
+    error::ApiError,
+};
+
 pub async fn data(Extension(building): Extension<Arc<Building>>) -> Response {
     json_resp(building.as_ref())
 }
@@ -29,17 +34,70 @@ where
 
 pub fn map_json_result<T, E>(result: Result<T, E>) -> serde_json::Value
 where
-    E: std::fmt::Display,
+    T: Serialize,
+    E: Into<ApiError>,
 {
     match result {
-        Ok(_) => json!({ "result": "success" }),
+        Ok(data) => success(data),
         Err(err) => fail(err),
     }
 }
 
+pub fn success<T>(data: T) -> serde_json::Value
+where
+    T: Serialize,
+{
+    json!({ "result": "success", "data": data })
+}
+
+/// Answers with the message of the error, its code and the values it is about,
+/// like the v2 API does.
 pub fn fail<E>(err: E) -> serde_json::Value
 where
-    E: std::fmt::Display,
+    E: Into<ApiError>,
 {
-    json!({ "result": "fail", "error": err.to_string() })
+    let ApiError {
+        code,
+        message,
+        details,
+        ..
+    } = err.into();
+    json!({ "result": "fail", "error": message, "code": code, "details": details })
+}
+
+/// Fault injection is only enabled when a token is configured, requests must carry it.
+pub fn check_fault_token(headers: &HeaderMap) -> Result<(), ApiError> {
+    match token_matches(headers, FAULT_TOKEN_ENV, FAULT_TOKEN_HEADER) {
+        None => Err(forbidden("fault injection is disabled")),
+        Some(false) => Err(forbidden("invalid fault injection token")),
+        Some(true) => Ok(()),
+    }
+}
+
+/// Admin commands are only enabled when a token is configured, requests must carry it.
+pub fn check_admin_token(headers: &HeaderMap) -> Result<(), ApiError> {
+    match token_matches(headers, ADMIN_TOKEN_ENV, ADMIN_TOKEN_HEADER) {
+        None => Err(forbidden("admin commands are disabled")),
+        Some(false) => Err(forbidden("invalid admin token")),
+        Some(true) => Ok(()),
+    }
+}
+
+fn forbidden(message: &str) -> ApiError {
+    ApiError::new(StatusCode::FORBIDDEN, "forbidden", message)
+}
+
+/// Returns whether the request carries the configured token, none if no token is configured.
+fn token_matches(headers: &HeaderMap, env: &str, header: &str) -> Option<bool> {
+    let token = std::env::var(env).ok()?;
+    Some(match headers.get(header) {
+        Some(value) => !token.is_empty() && constant_time_eq(value.as_bytes(), token.as_bytes()),
+        None => false,
+    })
+}
+
+/// Compares every byte whatever the first mismatch, so the time taken does not
+/// tell how much of a guessed token was right.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
 }
@@ -0,0 +1,163 @@
+use axum::{
+    extract::{
+        rejection::{JsonRejection, PathRejection},
+        Path,
+    },
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{delete, post, put},
+    Extension, Json, Router,
+};
+use serde::Serialize;
+use std::sync::Arc;
+
+use elevator_core::{
+    components::{enums::DoorStatus, model::Building},
+    ElevatorAction, Registration,
+};
+
+use super::{
+    error::ApiError,
+    extrators::{CarCallBody, DoorBody, DoorCommand, HallCallBody, HallDirection},
+    utils::json_resp,
+};
+
+/// Versioned REST API, mounted under `/api/v2`.
+pub fn router() -> Router {
+    Router::new()
+        .route("/elevators/:elevator_id/car-calls", post(car_call))
+        .route(
+            "/elevators/:elevator_id/car-calls/:floor",
+            delete(cancel_car_call),
+        )
+        .route("/elevators/:elevator_id/door", put(door))
+        .route("/floors/:floor/hall-calls", post(hall_call))
+        .route(
+            "/floors/:floor/hall-calls/:direction",
+            delete(cancel_hall_call),
+        )
+}
+
+/// Outcome of registering or cancelling a call.
+#[derive(Debug, Serialize)]
+pub struct CallResponse {
+    /// Car the call was submitted to, none for a hall call the group answers.
+    pub elevator_id: Option<u8>,
+    pub floor: u8,
+    /// Direction of the hall button, none for a car call.
+    pub direction: Option<HallDirection>,
+    pub registration: Registration,
+}
+
+impl IntoResponse for CallResponse {
+    fn into_response(self) -> Response {
+        let status = match self.registration {
+            Registration::Registered => StatusCode::CREATED,
+            Registration::AlreadyRegistered | Registration::Cancelled => StatusCode::OK,
+        };
+        (status, json_resp(&self)).into_response()
+    }
+}
+
+pub async fn car_call(
+    Extension(building): Extension<Arc<Building>>,
+    path: Result<Path<u8>, PathRejection>,
+    body: Result<Json<CarCallBody>, JsonRejection>,
+) -> Result<CallResponse, ApiError> {
+    let Path(elevator_id) = path?;
+    let Json(CarCallBody { floor }) = body?;
+    building.get_eleavtor(elevator_id)?;
+
+    Ok(CallResponse {
+        elevator_id: Some(elevator_id),
+        floor,
+        direction: None,
+        registration: building
+            .controller
+            .request_elevator(
+                elevator_id,
+                ElevatorAction::Request {
+                    floor,
+                    cancel: false,
+                },
+            )
+            .await?,
+    })
+}
+
+pub async fn cancel_car_call(
+    Extension(building): Extension<Arc<Building>>,
+    path: Result<Path<(u8, u8)>, PathRejection>,
+) -> Result<CallResponse, ApiError> {
+    let Path((elevator_id, floor)) = path?;
+    building.get_eleavtor(elevator_id)?;
+
+    Ok(CallResponse {
+        elevator_id: Some(elevator_id),
+        floor,
+        direction: None,
+        registration: building
+            .controller
+            .request_elevator(
+                elevator_id,
+                ElevatorAction::Request {
+                    floor,
+                    cancel: true,
+                },
+            )
+            .await?,
+    })
+}
+
+pub async fn hall_call(
+    Extension(building): Extension<Arc<Building>>,
+    path: Result<Path<u8>, PathRejection>,
+    body: Result<Json<HallCallBody>, JsonRejection>,
+) -> Result<CallResponse, ApiError> {
+    let Path(floor) = path?;
+    let Json(HallCallBody { direction }) = body?;
+
+    Ok(CallResponse {
+        elevator_id: None,
+        floor,
+        direction: Some(direction),
+        registration: building
+            .get_floor(floor)?
+            .hall_call(direction.into())
+            .await?,
+    })
+}
+
+pub async fn cancel_hall_call(
+    Extension(building): Extension<Arc<Building>>,
+    path: Result<Path<(u8, HallDirection)>, PathRejection>,
+) -> Result<CallResponse, ApiError> {
+    let Path((floor, direction)) = path?;
+    building.get_floor(floor)?;
+
+    Ok(CallResponse {
+        elevator_id: None,
+        floor,
+        direction: Some(direction),
+        registration: building
+            .controller
+            .cancel_hall_call(floor, direction.into())
+            .await?,
+    })
+}
+
+/// Presses the open or close button of a car, the doors move on the next
+/// self check.
+pub async fn door(
+    Extension(building): Extension<Arc<Building>>,
+    path: Result<Path<u8>, PathRejection>,
+    body: Result<Json<DoorBody>, JsonRejection>,
+) -> Result<StatusCode, ApiError> {
+    let Path(elevator_id) = path?;
+    let Json(DoorBody { command }) = body?;
+
+    let status = DoorStatus::from(matches!(command, DoorCommand::Open));
+    building.controller.door_control(elevator_id, status)?;
+
+    Ok(StatusCode::ACCEPTED)
+}
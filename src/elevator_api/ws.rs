@@ -0,0 +1,187 @@
+use axum::{
+    extract::ws::{Message, WebSocket, WebSocketUpgrade},
+    response::IntoResponse,
+    Extension,
+};
+use serde_json::{json, Value};
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{
+    sync::broadcast::{
+        self,
+        error::{RecvError, TryRecvError},
+    },
+    time::MissedTickBehavior,
+};
+
+use elevator_core::{components::event::EventRecord, components::model::Building};
+
+use super::constants::{WS_CHANNEL_CAPACITY, WS_MOTION_INTERVAL_MS};
+
+/// A value of the building state that changed, addressed by its JSON pointer.
+pub type Change = (String, Value);
+
+pub async fn ws(
+    ws: WebSocketUpgrade,
+    Extension(feed): Extension<Arc<StateFeed>>,
+) -> impl IntoResponse {
+    ws.on_upgrade(move |socket| push_state(socket, feed))
+}
+
+/// Changes of the building state, serialized once for every WebSocket client.
+///
+/// The state is compared on every event of the cars, and every
+/// [`WS_MOTION_INTERVAL_MS`] while a car moves so its position follows smoothly.
+pub struct StateFeed {
+    building: Arc<Building>,
+    /// Latest state sent, the snapshot of a new client.
+    state: Mutex<Value>,
+    sender: broadcast::Sender<Arc<str>>,
+}
+
+impl StateFeed {
+    /// Starts publishing the changes of the building until its event bus is gone.
+    pub fn spawn(building: Arc<Building>) -> Arc<Self> {
+        // Subscribed before the first state is taken, so no change slips in between
+        let events = building.controller.events.subscribe();
+        let feed = Arc::new(Self {
+            state: Mutex::new(serde_json::to_value(building.as_ref()).unwrap()),
+            building,
+            sender: broadcast::channel(WS_CHANNEL_CAPACITY).0,
+        });
+        tokio::spawn(feed.clone().run(events));
+        feed
+    }
+
+    async fn run(self: Arc<Self>, mut events: broadcast::Receiver<EventRecord>) {
+        let mut motion = tokio::time::interval(Duration::from_millis(WS_MOTION_INTERVAL_MS));
+        motion.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        loop {
+            let moving = self
+                .building
+                .controller
+                .elevators
+                .values()
+                .any(|elevator| !elevator.is_idle());
+            let mut records = Vec::new();
+            let mut lagged = false;
+            tokio::select! {
+                record = events.recv() => match record {
+                    Ok(record) => records.push(record),
+                    Err(RecvError::Lagged(_)) => lagged = true,
+                    Err(RecvError::Closed) => return,
+                },
+                _ = motion.tick(), if moving => {}
+            }
+            // Events published together are sent as one message
+            loop {
+                match events.try_recv() {
+                    Ok(record) => records.push(record),
+                    Err(TryRecvError::Lagged(_)) => lagged = true,
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Closed) => return,
+                }
+            }
+            self.publish(records, lagged);
+        }
+    }
+
+    /// Sends the events and the values that changed, or the whole state when
+    /// events were missed.
+    fn publish(&self, events: Vec<EventRecord>, lagged: bool) {
+        let next = serde_json::to_value(self.building.as_ref()).unwrap();
+        let mut state = self.state.lock().unwrap();
+        let message = if lagged {
+            json!({ "type": "snapshot", "data": next })
+        } else {
+            let mut changes = Vec::new();
+            diff("", &state, &next, &mut changes);
+            if changes.is_empty() && events.is_empty() {
+                return;
+            }
+            let changes = changes
+                .into_iter()
+                .map(|(path, value)| json!({ "path": path, "value": value }))
+                .collect::<Vec<Value>>();
+            json!({ "type": "changes", "events": events, "changes": changes })
+        };
+        *state = next;
+        // No client connected is fine
+        let _ = self.sender.send(message.to_string().into());
+    }
+
+    /// Subscribes to the changes following the returned snapshot.
+    pub fn subscribe(&self) -> (Arc<str>, broadcast::Receiver<Arc<str>>) {
+        let state = self.state.lock().unwrap();
+        let snapshot = json!({ "type": "snapshot", "data": *state });
+        (snapshot.to_string().into(), self.sender.subscribe())
+    }
+}
+
+/// Sends a snapshot of the building, then the changes as they are published.
+///
+/// A client falling behind the changes gets a fresh snapshot instead.
+async fn push_state(mut socket: WebSocket, feed: Arc<StateFeed>) {
+    let (snapshot, mut receiver) = feed.subscribe();
+    if !send(&mut socket, &snapshot).await {
+        return;
+    }
+
+    loop {
+        let message = tokio::select! {
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_)) | Err(_)) | None => return,
+                _ => continue,
+            },
+            message = receiver.recv() => message,
+        };
+
+        let message = match message {
+            Ok(message) => message,
+            Err(RecvError::Lagged(_)) => {
+                let (snapshot, next) = feed.subscribe();
+                receiver = next;
+                snapshot
+            }
+            Err(RecvError::Closed) => return,
+        };
+        if !send(&mut socket, &message).await {
+            return;
+        }
+    }
+}
+
+/// Returns false once the client is gone.
+async fn send(socket: &mut WebSocket, message: &str) -> bool {
+    socket
+        .send(Message::Text(message.to_string()))
+        .await
+        .is_ok()
+}
+
+/// Lists the values that differ between two states, descending into objects
+/// and arrays of the same length.
+pub fn diff(path: &str, old: &Value, new: &Value, changes: &mut Vec<Change>) {
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) if old.len() == new.len() => {
+            for (key, value) in new {
+                // Keys are escaped as RFC 6901 asks, `~` first
+                let path = format!("{path}/{}", key.replace('~', "~0").replace('/', "~1"));
+                match old.get(key) {
+                    Some(old) => diff(&path, old, value, changes),
+                    None => changes.push((path, value.clone())),
+                }
+            }
+        }
+        (Value::Array(old), Value::Array(new)) if old.len() == new.len() => {
+            for (i, (old, new)) in old.iter().zip(new).enumerate() {
+                diff(&format!("{path}/{i}"), old, new, changes);
+            }
+        }
+        _ if old != new => changes.push((path.to_string(), new.clone())),
+        _ => {}
+    }
+}
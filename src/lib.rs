@@ -3,38 +3,75 @@ extern crate tracing;
 
 pub mod utils {
     pub mod atomic;
+    pub mod clock;
     pub mod common;
     pub mod constants;
     pub mod notify;
 }
 
 pub mod components {
+    pub mod dispatch;
+    pub mod door;
     pub mod enums;
     pub mod error;
+    pub mod event;
+    pub mod fault;
+    pub mod load;
+    pub mod metrics;
     pub mod model;
+    pub mod motion;
+    pub mod passenger;
+    pub mod schedule;
+    pub mod simulation;
+    pub mod traffic;
 }
 
-use crate::components::error::{ElevatorControllerError, ElevatorError};
+use crate::components::{
+    dispatch::{Call, DispatchStrategy, ElevatorSnapshot, HallCallQueue},
+    door::DoorTiming,
+    error::{ElevatorControllerError, ElevatorError},
+    event::{ElevatorEvent, EventBus, EventRecord},
+    fault::FaultCode,
+    load::CarCapacity,
+    metrics::{MetricsCollector, ServiceStats},
+    motion::{MotionProfile, MotionState},
+    passenger::{Passenger, PassengerRegistry},
+    schedule::{StopOrigin, StopSet},
+};
 use crate::utils::{
-    atomic::{Atomic, AtomicOption, AtomicValue, Bool, I16, U64, U8},
-    common::timestamp,
-    constants::MAX_DOOR_OPEN_SECS,
+    atomic::{Atomic, AtomicOperation, AtomicOption, AtomicValue, Bool, F64, I16, U64, U8},
+    clock::Clock,
     notify::SignalHandle,
 };
 
-use components::enums::{Direction, DoorStatus, MovingStatus};
+use components::enums::{Direction, DoorStatus, MovingStatus, OperatingMode};
 use serde::{ser::SerializeStruct, Serialize};
-use std::collections::VecDeque;
 use std::{collections::BTreeMap, sync::Arc, time::Duration};
-use tokio::sync::Mutex;
+use tokio::sync::{broadcast, Mutex};
 use tokio::task::JoinHandle;
-use utils::atomic::AtomicOperation;
-use utils::constants::ELEVATOR_SELF_CHECK_MS;
+use utils::constants::{
+    ARRIVAL_TOLERANCE, ARRIVED_PASSENGER_HISTORY_LEN, DEFAULT_RECOVERY_SPEED,
+    ELEVATOR_SELF_CHECK_MS,
+};
 
 #[derive(Debug, Default)]
 pub struct ElevatorController {
     pub display_offset: I16,
+    pub outside_buttons: Arc<BTreeMap<u8, OutSideButton>>,
     pub elevators: BTreeMap<u8, Arc<Elevator>>,
+    pub passengers: Arc<PassengerRegistry>,
+    pub metrics: Arc<MetricsCollector>,
+    pub events: Arc<EventBus>,
+    /// Hall calls the cars gave back, they are dispatched again.
+    pub handed_back: Arc<HallCallQueue>,
+    /// Fire alarm of the building, every car is recalled while it is active.
+    pub fire_alarm: Bool,
+    /// Floor the cars are recalled to on a fire alarm.
+    pub recall_floor: U8,
+    strategy: Box<dyn DispatchStrategy>,
+    clock: Clock,
+    /// Dispatches the hall calls given back while the cars run in real time.
+    dispatch_task: AtomicOption<JoinHandle<()>>,
 }
 
 impl Serialize for ElevatorController {
@@ -42,8 +79,18 @@ impl Serialize for ElevatorController {
     where
         S: serde::Serializer,
     {
-        let mut s = serializer.serialize_struct("ElevatorController", 2)?;
+        let mut s = serializer.serialize_struct("ElevatorController", 6)?;
         s.serialize_field("display_offset", &self.display_offset)?;
+        s.serialize_field("dispatch_strategy", self.strategy.name())?;
+        s.serialize_field("fire_alarm", &self.fire_alarm)?;
+        s.serialize_field("recall_floor", &self.recall_floor)?;
+        s.serialize_field(
+            "outside_buttons",
+            &self
+                .outside_buttons
+                .values()
+                .collect::<Vec<&OutSideButton>>(),
+        )?;
         s.serialize_field(
             "elevators",
             &self
@@ -58,67 +105,493 @@ impl Serialize for ElevatorController {
 
 impl ElevatorController {
     pub fn new(display_offset: i16, num_floors: u8, init_floor: u8, num_elevators: u8) -> Self {
-        let elevators = BTreeMap::from_iter(
-            (0..num_elevators).map(|i| (i, Arc::new(Elevator::new(i, num_floors, init_floor)))),
-        );
+        // Hall buttons belong to the floor, so every car shares the same lamps
+        let outside_buttons = Arc::new(OutSideButton::for_floors(num_floors));
+        let passengers = Arc::new(PassengerRegistry::default());
+        let metrics = Arc::new(MetricsCollector::default());
+        let events = Arc::new(EventBus::default());
+        let handed_back = Arc::new(HallCallQueue::default());
+        let clock = Clock::default();
+        metrics.reset(clock.now_ms());
+        let elevators = BTreeMap::from_iter((0..num_elevators).map(|i| {
+            (
+                i,
+                Arc::new(
+                    Elevator::with_outside_buttons(
+                        i,
+                        num_floors,
+                        init_floor,
+                        outside_buttons.clone(),
+                    )
+                    .with_passengers(passengers.clone())
+                    .with_metrics(metrics.clone())
+                    .with_events(events.clone())
+                    .with_handed_back(handed_back.clone()),
+                ),
+            )
+        }));
 
         Self {
             display_offset: display_offset.into(),
+            outside_buttons,
             elevators,
+            passengers,
+            metrics,
+            events,
+            handed_back,
+            fire_alarm: Default::default(),
+            recall_floor: Default::default(),
+            strategy: Default::default(),
+            clock,
+            dispatch_task: Default::default(),
+        }
+    }
+
+    /// Replaces the clock of the controller and every car, statistics start over.
+    pub fn with_clock(mut self, clock: Clock) -> Self {
+        for elevator in self.elevators.values() {
+            elevator.clock.set(clock.clone().into());
+        }
+        self.metrics.reset(clock.now_ms());
+        self.clock = clock;
+        self
+    }
+
+    /// Replaces the dispatch strategy used to assign calls to cars.
+    pub fn with_strategy(mut self, strategy: impl DispatchStrategy + 'static) -> Self {
+        self.strategy = Box::new(strategy);
+        self
+    }
+
+    /// Replaces the motion profile of every car.
+    pub fn with_motion_profile(
+        self,
+        profile: MotionProfile,
+    ) -> Result<Self, ElevatorControllerError> {
+        for elevator in self.elevators.values() {
+            elevator.set_motion_profile(profile.clone())?;
+        }
+        Ok(self)
+    }
+
+    /// Replaces the rated capacity of every car.
+    pub fn with_capacity(self, capacity: CarCapacity) -> Self {
+        for elevator in self.elevators.values() {
+            elevator.capacity.set(capacity.into());
+        }
+        self
+    }
+
+    /// Moves the floor the cars are recalled to on a fire alarm, the lowest floor by default.
+    pub fn with_recall_floor(self, floor: u8) -> Result<Self, ElevatorControllerError> {
+        self.get_outside_button(floor)?;
+        self.recall_floor.set(floor);
+        Ok(self)
+    }
+
+    /// Replaces the door timings of every car.
+    pub fn with_door_timing(self, timing: DoorTiming) -> Self {
+        for elevator in self.elevators.values() {
+            elevator.door_timing.set(timing.into());
+        }
+        self
+    }
+
+    pub fn strategy(&self) -> &dyn DispatchStrategy {
+        self.strategy.as_ref()
+    }
+
+    pub fn clock(&self) -> &Clock {
+        &self.clock
+    }
+
+    /// Receives every transition of the cars from now on, see [`ElevatorEvent`].
+    ///
+    /// A receiver falling too far behind misses events, [`EventBus::resume`]
+    /// catches up on the ones the bus kept.
+    pub fn subscribe(&self) -> broadcast::Receiver<EventRecord> {
+        self.events.subscribe()
+    }
+
+    /// Advances a manual clock by one step and runs a self check of every car,
+    /// in elevator id order.
+    ///
+    /// Fails on a real-time clock, the cars then check themselves from their own task.
+    pub async fn step(&self, elapsed: Duration) -> Result<(), ElevatorControllerError> {
+        if !self.clock.is_manual() {
+            return Err(ElevatorControllerError::RealtimeClock);
+        }
+
+        self.clock.advance(elapsed);
+        for elevator in self.elevators.values() {
+            elevator.tick(elapsed).await;
+        }
+        self.reassign_hall_calls(self.handed_back.drain()).await;
+        Ok(())
+    }
+
+    /// Runs the cars on a manual clock for a while, one self check interval at a time.
+    pub async fn advance(&self, duration: Duration) -> Result<(), ElevatorControllerError> {
+        let interval = Duration::from_millis(ELEVATOR_SELF_CHECK_MS);
+        let mut remaining = duration;
+        while !remaining.is_zero() {
+            let elapsed = remaining.min(interval);
+            self.step(elapsed).await?;
+            remaining -= elapsed;
+        }
+        Ok(())
+    }
+
+    /// Takes a snapshot of every car, in elevator id order.
+    pub async fn snapshot(&self) -> Vec<ElevatorSnapshot> {
+        let mut snapshots = Vec::with_capacity(self.elevators.len());
+        for elevator in self.elevators.values() {
+            snapshots.push(elevator.snapshot().await);
         }
+        snapshots
+    }
+
+    /// Asks the dispatch strategy which car should serve the call.
+    pub async fn dispatch(&self, call: Call) -> Result<&Arc<Elevator>, ElevatorControllerError> {
+        let elevator_id = self
+            .strategy
+            .assign(&call, &self.snapshot().await)
+            .ok_or(ElevatorControllerError::NoElevatorAvailable)?;
+
+        self.get_elevator(elevator_id)
     }
 
     pub fn get_elevator(&self, elevator_id: u8) -> Result<&Arc<Elevator>, ElevatorControllerError> {
         self.elevators
             .get(&elevator_id)
-            .ok_or(ElevatorControllerError::GetElevatorError)
+            .ok_or(ElevatorControllerError::GetElevatorError {
+                elevator_id,
+                limit: self.elevators.len().saturating_sub(1) as u8,
+            })
+    }
+
+    pub fn get_outside_button(&self, floor: u8) -> Result<&OutSideButton, ElevatorControllerError> {
+        Ok(self
+            .outside_buttons
+            .get(&floor)
+            .ok_or(ElevatorError::FloorButtonNotExists {
+                floor,
+                limit: self.outside_buttons.len().saturating_sub(1) as u8,
+            })?)
+    }
+
+    /// Computes the service statistics since the controller was created or reset.
+    pub async fn stats(&self) -> ServiceStats {
+        let elevator_ids = self.elevators.keys().copied().collect::<Vec<u8>>();
+        let passengers = self.passengers.list().await;
+        self.metrics
+            .stats(&elevator_ids, &passengers, self.clock.now_ms())
     }
 
-    pub fn get_outside_button(
+    pub fn reset_stats(&self) {
+        self.metrics.reset(self.clock.now_ms());
+    }
+
+    /// Adds a passenger at the origin floor, who presses the hall button towards
+    /// the destination and rides the first car heading that way.
+    pub async fn add_passenger(
         &self,
-        elevator_id: u8,
-        floor: u8,
-    ) -> Result<&OutSideButton, ElevatorControllerError> {
+        origin: u8,
+        destination: u8,
+    ) -> Result<Passenger, ElevatorControllerError> {
+        self.get_outside_button(destination)?;
+        if origin == destination {
+            return Err(ElevatorControllerError::InvalidPassenger {
+                origin,
+                destination,
+            });
+        }
+
+        let direction = if destination > origin {
+            Direction::Up
+        } else {
+            Direction::Down
+        };
+        self.hall_call(origin, direction).await?;
+
+        // The registry only grows here, keep a bounded history of arrivals
+        self.passengers
+            .prune_arrived(ARRIVED_PASSENGER_HISTORY_LEN)
+            .await;
         Ok(self
-            .elevators
-            .get(&elevator_id)
-            .ok_or(ElevatorControllerError::GetElevatorError)?
-            .outside_button(floor)?)
+            .passengers
+            .add(origin, destination, self.clock.now_ms())
+            .await)
+    }
+
+    /// Registers a hall call and assigns it to the car with the lowest estimated cost.
+    ///
+    /// Pressing an already lit hall button does nothing, the call is already assigned.
+    pub async fn hall_call(
+        &self,
+        floor: u8,
+        direction: Direction,
+    ) -> Result<Registration, ElevatorControllerError> {
+        let outside_button = self.get_outside_button(floor)?;
+        outside_button.check_direction(direction)?;
+
+        if outside_button.is_active(direction) {
+            return Ok(Registration::AlreadyRegistered);
+        }
+
+        let elevator = self.dispatch(Call::Hall { floor, direction }).await?;
+
+        info!(
+            "[controller]: hall call {:?} at floor {} assigned to elevator {} ({})",
+            direction,
+            floor,
+            elevator.id.val(),
+            self.strategy.name()
+        );
+        Ok(elevator
+            .submit(ElevatorAction::Call {
+                floor,
+                direction,
+                cancel: false,
+            })
+            .await?)
+    }
+
+    /// Cancels a hall call on the car it was assigned to.
+    pub async fn cancel_hall_call(
+        &self,
+        floor: u8,
+        direction: Direction,
+    ) -> Result<Registration, ElevatorControllerError> {
+        let action = ElevatorAction::Call {
+            floor,
+            direction,
+            cancel: true,
+        };
+
+        // A faulted car gave its hall calls back and refuses any request
+        for elevator in self.elevators.values() {
+            if elevator.is_faulted() {
+                continue;
+            }
+            match elevator.submit(action.clone()).await {
+                Err(ElevatorError::NothingToCancel { .. }) => continue,
+                result => return Ok(result?),
+            }
+        }
+
+        Err(ElevatorError::NothingToCancel { floor }.into())
     }
 
     pub async fn request_elevator(
         &self,
         elevator_id: u8,
         action: ElevatorAction,
-    ) -> Result<(), ElevatorControllerError> {
-        let elevator = self.get_elevator(elevator_id)?;
+    ) -> Result<Registration, ElevatorControllerError> {
         match action {
-            ElevatorAction::Request { floor, cancel: _ } => {
-                elevator.inside_button(floor)?.active.set_true()
+            ElevatorAction::Request { floor, cancel: _ } => Ok(self
+                .dispatch(Call::Car { elevator_id, floor })
+                .await?
+                .submit(action)
+                .await?),
+            ElevatorAction::Call {
+                floor,
+                direction,
+                cancel: true,
+            } => self.cancel_hall_call(floor, direction).await,
+            ElevatorAction::Call {
+                floor,
+                direction,
+                cancel: false,
+            } => self.hall_call(floor, direction).await,
+        }
+    }
+
+    pub fn door_control(&self, id: u8, status: DoorStatus) -> Result<(), ElevatorControllerError> {
+        let elevator = self.get_elevator(id)?;
+        elevator.door_control(status)?;
+
+        Ok(())
+    }
+
+    pub fn door_obstruction(
+        &self,
+        id: u8,
+        obstructed: bool,
+    ) -> Result<(), ElevatorControllerError> {
+        let elevator = self.get_elevator(id)?;
+        elevator.set_door_obstruction(obstructed);
+
+        Ok(())
+    }
+
+    /// Releases the door button of a car held in firefighter service.
+    pub fn door_release(&self, id: u8) -> Result<(), ElevatorControllerError> {
+        let elevator = self.get_elevator(id)?;
+        elevator.door_release();
+
+        Ok(())
+    }
+
+    /// Halts a car where it is, see [`Elevator::emergency_stop`], its hall
+    /// calls are assigned to the other cars.
+    pub async fn emergency_stop(&self, id: u8) -> Result<(), ElevatorControllerError> {
+        self.inject_fault(id, FaultCode::EmergencyStop).await
+    }
+
+    /// Makes a car fail, for resilience testing, see [`Elevator::inject_fault`].
+    ///
+    /// The hall calls of the car are assigned to the other cars.
+    pub async fn inject_fault(
+        &self,
+        id: u8,
+        code: FaultCode,
+    ) -> Result<(), ElevatorControllerError> {
+        let elevator = self.get_elevator(id)?;
+        elevator.inject_fault(code);
+        let hall_calls = elevator.hand_back_hall_calls().await;
+        self.reassign_hall_calls(hall_calls).await;
+
+        Ok(())
+    }
+
+    /// Resets the fault of a car, see [`Elevator::reset_fault`].
+    pub fn reset_fault(&self, id: u8) -> Result<(), ElevatorControllerError> {
+        let elevator = self.get_elevator(id)?;
+        elevator.reset_fault()?;
+
+        Ok(())
+    }
+
+    /// Switches the operating mode of a car, the hall calls it leaves behind
+    /// are assigned to the other cars.
+    ///
+    /// A car put back in normal operation during a fire alarm is recalled instead.
+    pub async fn set_operating_mode(
+        &self,
+        id: u8,
+        mode: OperatingMode,
+    ) -> Result<(), ElevatorControllerError> {
+        let elevator = self.get_elevator(id)?;
+        if mode == OperatingMode::Normal
+            && self.fire_alarm.val()
+            && !elevator.mode().is_fire_service()
+        {
+            return Ok(elevator.fire_recall(self.recall_floor.val()).await?);
+        }
+
+        let hall_calls = elevator.set_mode(mode).await?;
+        self.reassign_hall_calls(hall_calls).await;
+        Ok(())
+    }
+
+    /// Assigns hall calls handed back by a car again, the calls no car can
+    /// take are dropped.
+    async fn reassign_hall_calls(&self, hall_calls: Vec<(u8, Direction)>) {
+        for (floor, direction) in hall_calls {
+            if let Err(err) = self.hall_call(floor, direction).await {
+                warn!(
+                    "[controller]: hall call {:?} at floor {} dropped: {}",
+                    direction, floor, err
+                );
+                self.metrics.hall_call_cancelled(floor, direction);
+            }
+        }
+    }
+
+    /// Raises or clears the fire alarm of the building.
+    ///
+    /// Raising it recalls the cars in normal operation or independent service
+    /// to the recall floor (fire service Phase I), dropping their stops and the
+    /// hall calls. Cars under inspection or out of service are left alone.
+    /// Clearing it puts the recalled cars back in normal operation, cars under
+    /// firefighter control stay so until the key is switched off.
+    pub async fn set_fire_alarm(&self, active: bool) -> Result<(), ElevatorControllerError> {
+        if self.fire_alarm.val() == active {
+            return Ok(());
+        }
+        self.fire_alarm.set(active);
+        self.events
+            .publish(ElevatorEvent::FireAlarm { active }, self.clock.now_ms());
+
+        if active {
+            warn!(
+                "[controller]: fire alarm, recalling every car to floor {}",
+                self.recall_floor.val()
+            );
+        } else {
+            info!("[controller]: fire alarm cleared");
+        }
+        for elevator in self.elevators.values() {
+            match elevator.mode() {
+                OperatingMode::Normal | OperatingMode::Independent if active => {
+                    elevator.fire_recall(self.recall_floor.val()).await?
+                }
+                OperatingMode::FireRecall if !active => elevator.end_fire_service().await,
+                _ => {}
             }
         }
-        elevator.submit(action).await;
 
         Ok(())
     }
 
-    pub fn door_control(&self, id: u8, status: DoorStatus) -> Result<(), ElevatorControllerError> {
+    /// Switches the firefighter key of a car on or off (fire service Phase II).
+    ///
+    /// The key only works on a recalled car. Switched off, the car is recalled
+    /// again while the fire alarm lasts, otherwise back in normal operation.
+    pub async fn firefighter_service(
+        &self,
+        id: u8,
+        on: bool,
+    ) -> Result<(), ElevatorControllerError> {
         let elevator = self.get_elevator(id)?;
-        elevator.door_control(status);
+        if on {
+            elevator.firefighter_service()?;
+        } else if elevator.mode() == OperatingMode::Firefighter {
+            if self.fire_alarm.val() {
+                elevator.fire_recall(self.recall_floor.val()).await?;
+            } else {
+                elevator.end_fire_service().await;
+            }
+        }
 
         Ok(())
     }
 
-    pub async fn start_all_elevators(&self) {
+    /// Starts the self check task of every car, and the task dispatching the
+    /// hall calls they give back. Fails on a manual clock, whose cars are
+    /// stepped with [`ElevatorController::advance`] instead.
+    pub async fn start_all_elevators(self: &Arc<Self>) -> Result<(), ElevatorControllerError> {
         for ele in self.elevators.values() {
-            Elevator::start(ele).unwrap()
+            Elevator::start(ele)?;
+        }
+
+        let controller = self.clone();
+        let task = tokio::spawn(async move {
+            loop {
+                let hall_calls = controller.handed_back.wait().await;
+                controller.reassign_hall_calls(hall_calls).await;
+            }
+        });
+        if let Some(task) = self.dispatch_task.load().as_ref() {
+            task.abort();
         }
+        self.dispatch_task.set(Some(task.into()));
+        Ok(())
     }
 
-    pub async fn stop_all_elevators(&self) {
+    pub async fn stop_all_elevators(&self) -> Result<(), ElevatorControllerError> {
+        if self.clock.is_manual() {
+            return Err(ElevatorError::ManualClock.into());
+        }
         for ele in self.elevators.values() {
-            ele.stop().unwrap()
+            ele.stop()?;
+        }
+        if let Some(task) = self.dispatch_task.load().as_ref() {
+            task.abort();
         }
+        self.dispatch_task.set(None);
+        Ok(())
     }
 }
 
@@ -128,9 +601,28 @@ pub enum PanelRequest {
     ResetDownButton,
 }
 
+/// Outcome of submitting an action to a car.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Registration {
+    /// A new stop was added.
+    Registered,
+    /// The stop was already pending, the request was merged into it.
+    AlreadyRegistered,
+    /// The stop was removed.
+    Cancelled,
+}
+
 #[derive(Debug, Clone)]
 pub enum ElevatorAction {
+    /// Car call from the inside button panel.
     Request { floor: u8, cancel: bool },
+    /// Hall call from the outside button panel.
+    Call {
+        floor: u8,
+        direction: Direction,
+        cancel: bool,
+    },
 }
 
 pub trait CheckFloor {
@@ -142,15 +634,58 @@ pub trait CheckFloor {
 pub struct Elevator {
     pub id: U8,
     pub top_floor: U8,
-    pub outside_buttons: BTreeMap<u8, OutSideButton>,
+    pub outside_buttons: Arc<BTreeMap<u8, OutSideButton>>,
     pub inside_buttons: BTreeMap<u8, InSideButton>,
     pub floor: U8,
     pub moving_status: Atomic<MovingStatus>,
     pub door_status: Atomic<DoorStatus>,
-    pub door_last_open: U64,
-    pub current_action: AtomicOption<ElevatorAction>,
-
-    action_queue: Mutex<VecDeque<ElevatorAction>>,
+    /// When the doors entered their current status, in milliseconds.
+    pub door_last_change: U64,
+    pub door_timing: Atomic<DoorTiming>,
+    /// Light curtain across the doorway is interrupted.
+    pub door_obstructed: Bool,
+    /// Times the doors reopened on an obstruction since they were last closed.
+    pub door_reopens: U8,
+    /// The doors ignore the light curtain and close slowly.
+    pub door_nudging: Bool,
+    /// Door button held by a firefighter, the doors only move while it is pressed.
+    pub door_button: AtomicOption<DoorStatus>,
+    /// A car call was pressed since the car last stopped, an independent car
+    /// keeps its doors open until then.
+    car_call_pressed: Bool,
+    pub mode: Atomic<OperatingMode>,
+    /// Fault latched by the car, it stands still and rejects every action until reset.
+    pub fault: AtomicOption<FaultCode>,
+    /// The car creeps to the nearest floor after a fault was reset.
+    pub recovering: Bool,
+    /// Direction of the current collective run, none if the car has nothing to do.
+    pub heading: AtomicOption<Direction>,
+    /// Floor the car is currently driving to.
+    pub target_floor: AtomicOption<u8>,
+    pub motion_profile: Atomic<MotionProfile>,
+    /// Position above the lowest floor, in metres.
+    pub position: F64,
+    /// Signed velocity, positive going up, in m/s.
+    pub velocity: F64,
+    /// Signed acceleration, positive going up, in m/s².
+    pub acceleration: F64,
+    pub capacity: Atomic<CarCapacity>,
+    /// Load measured by the weighing device, in kg.
+    pub load_kg: F64,
+    /// Persons inside the car.
+    pub persons: U8,
+    /// The load exceeds the rated capacity, the buzzer sounds.
+    pub overloaded: Bool,
+    pub clock: Atomic<Clock>,
+    pub passengers: Arc<PassengerRegistry>,
+    pub metrics: Arc<MetricsCollector>,
+    pub events: Arc<EventBus>,
+    /// Hall calls the car gives back to the controller, to be dispatched again.
+    pub handed_back: Arc<HallCallQueue>,
+
+    stops: Mutex<StopSet>,
+    /// Held while the doors are commanded or the car departs, so the two never overlap.
+    door_interlock: std::sync::Mutex<()>,
     handle: AtomicOption<JoinHandle<Option<()>>>,
     signal: AtomicOption<SignalHandle>,
 }
@@ -160,16 +695,9 @@ impl Serialize for Elevator {
     where
         S: serde::Serializer,
     {
-        let mut s = serializer.serialize_struct("Elevator", 7)?;
+        let mut s = serializer.serialize_struct("Elevator", 19)?;
         s.serialize_field("id", &self.id)?;
         s.serialize_field("top_floor", &self.top_floor)?;
-        s.serialize_field(
-            "outside_buttons",
-            &self
-                .outside_buttons
-                .values()
-                .collect::<Vec<&OutSideButton>>(),
-        )?;
         s.serialize_field(
             "inside_buttons",
             &self.inside_buttons.values().collect::<Vec<&InSideButton>>(),
@@ -177,7 +705,19 @@ impl Serialize for Elevator {
         s.serialize_field("floor", &self.floor)?;
         s.serialize_field("moving_status", &self.moving_status)?;
         s.serialize_field("door_status", &self.door_status)?;
-        s.serialize_field("door_last_open", &self.door_last_open)?;
+        s.serialize_field("door_last_change", &self.door_last_change)?;
+        s.serialize_field("door_obstructed", &self.door_obstructed)?;
+        s.serialize_field("door_nudging", &self.door_nudging)?;
+        s.serialize_field("mode", &self.mode)?;
+        s.serialize_field("fault", &self.fault)?;
+        s.serialize_field("recovering", &self.recovering)?;
+        s.serialize_field("heading", &self.heading)?;
+        s.serialize_field("target_floor", &self.target_floor)?;
+        s.serialize_field("position", &self.position)?;
+        s.serialize_field("velocity", &self.velocity)?;
+        s.serialize_field("load_kg", &self.load_kg)?;
+        s.serialize_field("persons", &self.persons)?;
+        s.serialize_field("overloaded", &self.overloaded)?;
         s.end()
     }
 }
@@ -194,21 +734,93 @@ impl CheckFloor for Elevator {
 
 impl Elevator {
     pub fn new(id: u8, num_floors: u8, init_floor: u8) -> Self {
+        Self::with_outside_buttons(
+            id,
+            num_floors,
+            init_floor,
+            Arc::new(OutSideButton::for_floors(num_floors)),
+        )
+    }
+
+    /// Creates an elevator that shares its hall buttons with other cars.
+    pub fn with_outside_buttons(
+        id: u8,
+        num_floors: u8,
+        init_floor: u8,
+        outside_buttons: Arc<BTreeMap<u8, OutSideButton>>,
+    ) -> Self {
         let top_floor = num_floors - 1;
+        let motion_profile = MotionProfile::for_floors(num_floors);
         Self {
             id: id.into(),
             top_floor: top_floor.into(),
-            outside_buttons: (0..num_floors)
-                .map(|floor| (floor, OutSideButton::new(floor, top_floor)))
-                .collect(),
+            outside_buttons,
             inside_buttons: (0..num_floors)
                 .map(|floor| (floor, InSideButton::new(floor)))
                 .collect(),
             floor: init_floor.into(),
+            position: motion_profile.floor_position(init_floor).into(),
+            motion_profile: motion_profile.into(),
             ..Default::default()
         }
     }
 
+    /// Shares the passengers of the building with the car.
+    pub fn with_passengers(mut self, passengers: Arc<PassengerRegistry>) -> Self {
+        self.passengers = passengers;
+        self
+    }
+
+    /// Records the hall calls and trips of the car into shared metrics.
+    pub fn with_metrics(mut self, metrics: Arc<MetricsCollector>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Publishes the events of the car on a shared bus.
+    pub fn with_events(mut self, events: Arc<EventBus>) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// Gives the hall calls the car cannot answer back to a shared queue.
+    pub fn with_handed_back(mut self, handed_back: Arc<HallCallQueue>) -> Self {
+        self.handed_back = handed_back;
+        self
+    }
+
+    fn emit(&self, event: ElevatorEvent) {
+        self.events.publish(event, self.now_ms());
+    }
+
+    /// Replaces the motion profile, the car has to stand still at a floor.
+    pub fn set_motion_profile(&self, profile: MotionProfile) -> Result<(), ElevatorError> {
+        if !self.is_idle() {
+            return Err(ElevatorError::CannotChangeMotionProfile);
+        }
+        if !profile.is_valid() || profile.num_floors() != self.inside_buttons.len() {
+            return Err(ElevatorError::InvalidMotionProfile);
+        }
+
+        self.position.set(profile.floor_position(self.floor.val()));
+        self.motion_profile.set(profile.into());
+        Ok(())
+    }
+
+    pub fn motion(&self) -> MotionState {
+        MotionState {
+            position: self.position.val(),
+            velocity: self.velocity.val(),
+            acceleration: self.acceleration.val(),
+        }
+    }
+
+    fn set_motion(&self, motion: MotionState) {
+        self.position.set(motion.position);
+        self.velocity.set(motion.velocity);
+        self.acceleration.set(motion.acceleration);
+    }
+
     /// Returns true if the background service is started, false otherwise.
     pub fn is_started(&self) -> bool {
         match self.handle.load().as_ref() {
@@ -232,13 +844,19 @@ impl Elevator {
     pub fn outside_button(&self, floor: u8) -> Result<&OutSideButton, ElevatorError> {
         self.outside_buttons
             .get(&floor)
-            .ok_or(ElevatorError::FloorButtonNotExists)
+            .ok_or(ElevatorError::FloorButtonNotExists {
+                floor,
+                limit: self.top_floor.val(),
+            })
     }
 
     pub fn inside_button(&self, floor: u8) -> Result<&InSideButton, ElevatorError> {
         self.inside_buttons
             .get(&floor)
-            .ok_or(ElevatorError::FloorButtonNotExists)
+            .ok_or(ElevatorError::FloorButtonNotExists {
+                floor,
+                limit: self.top_floor.val(),
+            })
     }
 
     pub fn signal(&self) -> Option<Arc<SignalHandle>> {
@@ -249,118 +867,444 @@ impl Elevator {
         self.door_status.load().as_ref() == &DoorStatus::Open
     }
 
+    pub fn is_door_closed(&self) -> bool {
+        self.door_status.load().as_ref() == &DoorStatus::Closed
+    }
+
+    /// Current time of the car clock, in milliseconds.
+    pub fn now_ms(&self) -> u64 {
+        self.clock.load().now_ms()
+    }
+
+    pub fn heading(&self) -> Option<Direction> {
+        self.heading.load().as_deref().copied()
+    }
+
+    pub fn mode(&self) -> OperatingMode {
+        *self.mode.load().as_ref()
+    }
+
+    pub fn is_faulted(&self) -> bool {
+        self.fault.load().is_some()
+    }
+
+    /// The fault the car stopped on, none while it is in service.
+    pub fn fault(&self) -> Option<FaultCode> {
+        self.fault.load().as_deref().copied()
+    }
+
+    /// Halts the car where it is, even between floors, and latches the fault.
+    ///
+    /// The car then rejects every action with [`ElevatorError::Faulted`] until
+    /// the fault is reset, its pending stops are kept.
+    pub fn emergency_stop(&self) {
+        self.inject_fault(FaultCode::EmergencyStop);
+    }
+
+    /// Makes the car fail like the emergency stop does, for resilience testing.
+    ///
+    /// The car keeps the first fault until it is reset and the doors stay as
+    /// they are. It stops dead, but on [`FaultCode::DoorStuck`] which only
+    /// freezes the doors, see [`FaultCode`].
+    pub fn inject_fault(&self, code: FaultCode) {
+        let _interlock = self.door_interlock.lock().unwrap();
+        if self.is_faulted() {
+            return;
+        }
+
+        self.fault.set(Some(code.into()));
+        self.recovering.set_false();
+        if code != FaultCode::DoorStuck {
+            self.velocity.set(0.0);
+            self.acceleration.set(0.0);
+            if !self.is_idle() {
+                self.moving_status.set(MovingStatus::None.into());
+                self.target_floor.set(None);
+                self.metrics.trip_finished(self.id.val(), self.now_ms());
+            }
+        }
+        if code == FaultCode::StuckBetweenFloors {
+            self.slip_off_floor_level();
+        }
+        self.emit(ElevatorEvent::Fault {
+            elevator_id: self.id.val(),
+            code,
+        });
+        warn!(
+            "[elevator {}]: fault {:?} latched at {:.2} m",
+            self.id.val(),
+            code,
+            self.position.val()
+        );
+    }
+
+    /// Removes the hall calls of the car and turns their lamps off, so they can
+    /// be assigned to another car.
+    pub async fn hand_back_hall_calls(&self) -> Vec<(u8, Direction)> {
+        let mut stops = self.stops.lock().await;
+        self.take_hall_calls(&mut stops)
+    }
+
+    /// Clears the latched fault and starts the recovery, the car creeps at
+    /// slow speed to the nearest floor and opens its doors there.
+    ///
+    /// A car that lost its position creeps down to the lowest floor instead.
+    pub fn reset_fault(&self) -> Result<(), ElevatorError> {
+        let _interlock = self.door_interlock.lock().unwrap();
+        let Some(code) = self.fault.load().as_deref().copied() else {
+            return Err(ElevatorError::NotFaulted);
+        };
+
+        let profile = self.motion_profile.load();
+        let position = self.position.val();
+        let nearest = profile.nearest_floor(position);
+        let floor = match code {
+            FaultCode::PositionLost => 0,
+            _ => nearest,
+        };
+        let to_go = profile.floor_position(floor) - position;
+        self.floor.set(nearest);
+        self.fault.set(None);
+        self.recovering.set_true();
+        self.target_floor.set(Some(floor.into()));
+        if to_go.abs() > ARRIVAL_TOLERANCE {
+            let direction = if to_go > 0.0 {
+                Direction::Up
+            } else {
+                Direction::Down
+            };
+            self.moving_status.set(MovingStatus::from(direction).into());
+        }
+        self.emit(ElevatorEvent::FaultReset {
+            elevator_id: self.id.val(),
+        });
+        info!(
+            "[elevator {}]: fault reset, recovering to floor {}",
+            self.id.val(),
+            floor
+        );
+        Ok(())
+    }
+
+    /// Moves a car standing level at a floor halfway to the next one, up unless
+    /// it is at the top floor.
+    fn slip_off_floor_level(&self) {
+        let profile = self.motion_profile.load();
+        let position = self.position.val();
+        let nearest = profile.nearest_floor(position);
+        if (profile.floor_position(nearest) - position).abs() > ARRIVAL_TOLERANCE {
+            return;
+        }
+
+        let next = if nearest < self.top_floor.val() {
+            nearest + 1
+        } else {
+            nearest.saturating_sub(1)
+        };
+        self.position
+            .set((profile.floor_position(nearest) + profile.floor_position(next)) / 2.0);
+    }
+
+    /// Reads the floor next to the nearest one every other self check.
+    fn flap_floor_sensor(&self) {
+        let nearest = self
+            .motion_profile
+            .load()
+            .nearest_floor(self.position.val());
+        let reading = if self.floor.val() != nearest {
+            nearest
+        } else if nearest < self.top_floor.val() {
+            nearest + 1
+        } else {
+            nearest.saturating_sub(1)
+        };
+        self.floor.set(reading);
+    }
+
+    /// Creeps to the target floor at slow speed after a fault, then opens the doors.
+    fn recover(&self, dt: f64) {
+        let profile = self.motion_profile.load_full();
+        let slow = MotionProfile {
+            rated_speed: profile.rated_speed.min(DEFAULT_RECOVERY_SPEED),
+            ..profile.as_ref().clone()
+        };
+        let floor = self
+            .target_floor
+            .load()
+            .as_deref()
+            .copied()
+            .unwrap_or_else(|| self.floor.val());
+
+        let mut motion = self.motion();
+        let arrived = motion.step(&slow, profile.floor_position(floor), dt);
+        self.set_motion(motion);
+        if !arrived {
+            return;
+        }
+
+        self.floor.set(floor);
+        self.target_floor.set(None);
+        self.moving_status.set(MovingStatus::None.into());
+        self.recovering.set_false();
+        self.emit(ElevatorEvent::FloorArrived {
+            elevator_id: self.id.val(),
+            floor,
+        });
+        self.operate_door(DoorStatus::Open);
+        info!("[elevator {}]: recovered at floor {}", self.id.val(), floor);
+    }
+
+    /// Fire service Phase I, drops every stop and returns non-stop to the
+    /// recall floor, where the car parks with its doors open.
+    ///
+    /// A car travelling away from the recall floor stops at the next floor it
+    /// can brake for and turns around without opening its doors.
+    pub async fn fire_recall(&self, floor: u8) -> Result<(), ElevatorError> {
+        self.inside_button(floor)?;
+
+        let mut stops = self.stops.lock().await;
+        self.change_mode(OperatingMode::FireRecall);
+        self.door_button.set(None);
+        self.clear_stops(&mut stops);
+        stops.insert(floor, StopOrigin::Car);
+        warn!(
+            "[elevator {}]: fire recall to floor {}",
+            self.id.val(),
+            floor
+        );
+        Ok(())
+    }
+
+    /// Fire service Phase II, hands a recalled car over to a firefighter.
+    pub fn firefighter_service(&self) -> Result<(), ElevatorError> {
+        let mode = self.mode();
+        if !mode.is_fire_service() {
+            return Err(ElevatorError::OperatingModeRejected {
+                mode,
+                requested: Some(OperatingMode::Firefighter),
+            });
+        }
+
+        self.change_mode(OperatingMode::Firefighter);
+        warn!("[elevator {}]: firefighter service", self.id.val());
+        Ok(())
+    }
+
+    /// Leaves fire service, the car is back in normal operation without any stop.
+    pub async fn end_fire_service(&self) {
+        let mut stops = self.stops.lock().await;
+        self.clear_stops(&mut stops);
+        self.door_button.set(None);
+        self.change_mode(OperatingMode::Normal);
+        info!("[elevator {}]: back in normal operation", self.id.val());
+    }
+
+    /// Switches between normal operation, independent service, inspection and
+    /// out of service, fire service has its own controls.
+    ///
+    /// Leaving normal operation hands the hall calls of the car back, so they
+    /// can be assigned to another car. Inspection also drops the car calls,
+    /// while a car taken out of service still delivers the passengers inside.
+    pub async fn set_mode(
+        &self,
+        mode: OperatingMode,
+    ) -> Result<Vec<(u8, Direction)>, ElevatorError> {
+        let current = self.mode();
+        if mode.is_fire_service() || current.is_fire_service() {
+            return Err(ElevatorError::OperatingModeRejected {
+                mode: current,
+                requested: Some(mode),
+            });
+        }
+
+        let mut stops = self.stops.lock().await;
+        self.change_mode(mode);
+        let hall_calls = if mode.accepts_hall_calls() {
+            Vec::new()
+        } else {
+            self.take_hall_calls(&mut stops)
+        };
+        if !mode.accepts_car_calls() && mode != OperatingMode::OutOfService {
+            self.take_car_calls(&mut stops);
+        }
+
+        info!("[elevator {}]: operating mode {:?}", self.id.val(), mode);
+        Ok(hall_calls)
+    }
+
+    fn change_mode(&self, mode: OperatingMode) {
+        if mode == self.mode() {
+            return;
+        }
+        self.mode.set(mode.into());
+        self.emit(ElevatorEvent::ModeChanged {
+            elevator_id: self.id.val(),
+            mode,
+        });
+    }
+
+    /// Removes the hall calls of the car and turns their lamps off, returns them.
+    fn take_hall_calls(&self, stops: &mut StopSet) -> Vec<(u8, Direction)> {
+        let up = std::mem::take(&mut stops.up);
+        let down = std::mem::take(&mut stops.down);
+        let hall_calls = up
+            .into_iter()
+            .map(|floor| (floor, Direction::Up))
+            .chain(down.into_iter().map(|floor| (floor, Direction::Down)))
+            .collect::<Vec<(u8, Direction)>>();
+
+        for &(floor, direction) in &hall_calls {
+            self.outside_button(floor)
+                .unwrap()
+                .set_active(direction, false);
+            self.emit(ElevatorEvent::CallCancelled {
+                elevator_id: self.id.val(),
+                floor,
+                direction: Some(direction),
+            });
+        }
+        hall_calls
+    }
+
+    /// Removes the car calls of the car and turns their lamps off.
+    fn take_car_calls(&self, stops: &mut StopSet) {
+        for floor in std::mem::take(&mut stops.car) {
+            self.inside_button(floor).unwrap().active.set_false();
+            self.emit(ElevatorEvent::CallCancelled {
+                elevator_id: self.id.val(),
+                floor,
+                direction: None,
+            });
+        }
+    }
+
+    /// Drops every stop of the car and turns their lamps off.
+    fn clear_stops(&self, stops: &mut StopSet) {
+        self.take_car_calls(stops);
+        for (floor, direction) in self.take_hall_calls(stops) {
+            self.metrics.hall_call_cancelled(floor, direction);
+        }
+    }
+
+    /// Takes a read-only snapshot of the car for dispatching.
+    pub async fn snapshot(&self) -> ElevatorSnapshot {
+        let targets = self
+            .stops
+            .lock()
+            .await
+            .route(self.floor.val(), self.heading());
+
+        ElevatorSnapshot {
+            id: self.id.val(),
+            floor: self.floor.val(),
+            moving_status: *self.moving_status.load().as_ref(),
+            mode: self.mode(),
+            faulted: self.is_faulted(),
+            targets,
+            load_ratio: self.load_ratio(),
+            full_load: self.is_full_load(),
+        }
+    }
+
+    /// Share of the capacity in use.
+    pub fn load_ratio(&self) -> f64 {
+        self.capacity
+            .load()
+            .load_ratio(self.load_kg.val(), self.persons.val())
+    }
+
+    /// Returns true if the car is too full to pick anyone up, it passes hall calls by.
+    pub fn is_full_load(&self) -> bool {
+        self.capacity
+            .load()
+            .is_full_load(self.load_kg.val(), self.persons.val())
+    }
+
+    /// Updates the load measured by the weighing device, in kg.
+    pub fn set_load(&self, load_kg: f64) {
+        self.load_kg.set(load_kg.max(0.0));
+        self.check_overload();
+    }
+
+    /// Sounds the buzzer while the car is overloaded, which holds the doors open.
+    fn check_overload(&self) {
+        let overloaded = self
+            .capacity
+            .load()
+            .is_overloaded(self.load_kg.val(), self.persons.val());
+        if overloaded == self.overloaded.val() {
+            return;
+        }
+
+        self.overloaded.set(overloaded);
+        self.emit(ElevatorEvent::Overloaded {
+            elevator_id: self.id.val(),
+            overloaded,
+        });
+        if overloaded {
+            warn!(
+                "[elevator {}]: overloaded ({:.0} kg, {} persons), buzzer on",
+                self.id.val(),
+                self.load_kg.val(),
+                self.persons.val()
+            );
+        } else {
+            info!(
+                "[elevator {}]: load back to normal, buzzer off",
+                self.id.val()
+            );
+        }
+    }
+
+    /// Returns true if the car has stops left to serve.
+    pub async fn has_stops(&self) -> bool {
+        !self.stops.lock().await.is_empty()
+    }
+
+    /// When the doors move on by themselves, none if they stay as they are.
+    pub fn door_deadline_ms(&self) -> Option<u64> {
+        if self.is_faulted() {
+            return None;
+        }
+        let status = *self.door_status.load().as_ref();
+        let nudging = self.door_nudging.val();
+        if status == DoorStatus::Open && self.door_obstructed.val() && !nudging {
+            return None;
+        }
+        if self.is_door_held(status) {
+            return None;
+        }
+
+        self.door_timing
+            .load()
+            .duration(status, nudging)
+            .map(|duration| self.door_last_change.val() + duration)
+    }
+
     pub fn start(elevator: &Arc<Elevator>) -> Result<(), ElevatorError> {
         if elevator.is_started() {
             return Err(ElevatorError::AlreadyStarted);
         }
+        if elevator.clock.load().is_manual() {
+            return Err(ElevatorError::ManualClock);
+        }
 
         let signal = SignalHandle::new();
         let signal_cloned = signal.clone();
         elevator.signal.set(Some(Arc::new(signal_cloned)));
 
-        let recv = {
-            let elevator = elevator.clone();
-            let self_check_interval = Duration::from_millis(ELEVATOR_SELF_CHECK_MS);
-
-            async move {
-                loop {
-                    if elevator.current_action.load().is_none() {
-                        if let Some(action) = elevator.action_queue.lock().await.pop_front() {
-                            info!("[elevator {}]: new action: {:?}", elevator.id.val(), action);
-                            elevator.current_action.set(Some(action.clone().into()));
-                        }
-                    }
-
-                    tokio::time::sleep(self_check_interval).await;
-                }
-            }
-        };
-
         let self_check = {
-            let mut distance = 0;
             let elevator = elevator.clone();
             let self_check_interval = Duration::from_millis(ELEVATOR_SELF_CHECK_MS);
 
             async move {
+                let mut last_check = elevator.now_ms();
                 loop {
-                    if elevator.is_door_open()
-                        && (timestamp() - elevator.door_last_open.val()) > MAX_DOOR_OPEN_SECS
-                    {
-                        elevator.door_control(DoorStatus::Close)
-                    }
-
-                    let current_floor = elevator.floor.val();
-                    if let Some(current_action) = elevator.current_action.val().as_deref().cloned()
-                    {
-                        match current_action {
-                            ElevatorAction::Request { floor, cancel: _ } => {
-                                if floor > current_floor {
-                                    if !elevator.is_moving_up() {
-                                        elevator.moving_status.set(MovingStatus::Up.into());
-                                        info!("[elevator {}]: moving up", elevator.id.val());
-                                    }
-                                } else if floor < current_floor {
-                                    if !elevator.is_moving_down() {
-                                        elevator.moving_status.set(MovingStatus::Down.into());
-                                        info!("[elevator {}]: moving down", elevator.id.val());
-                                    }
-                                } else {
-                                    elevator.current_action.set(None);
-
-                                    if !elevator.is_idle() {
-                                        elevator.moving_status.set(MovingStatus::None.into());
-                                        info!("[elevator {}]: stop moving", elevator.id.val());
-                                    }
-
-                                    elevator.door_control(DoorStatus::Open);
-
-                                    elevator
-                                        .outside_button(current_floor)
-                                        .unwrap()
-                                        .down
-                                        .set_false();
-                                    elevator
-                                        .outside_button(current_floor)
-                                        .unwrap()
-                                        .up
-                                        .set_false();
-                                    elevator
-                                        .inside_button(current_floor)
-                                        .unwrap()
-                                        .active
-                                        .set_false();
-                                }
-                            }
-                        }
-                    }
-
-                    let moving_status = *elevator.moving_status.val().as_ref();
-
-                    if distance >= 600 {
-                        distance = 0;
-                        match moving_status {
-                            MovingStatus::Up => {
-                                elevator.floor.add(1);
-                            }
-                            MovingStatus::Down => {
-                                elevator.floor.sub(1);
-                            }
-                            _ => {}
-                        }
-                        info!(
-                            "[elevator {}]: arrived floor {}",
-                            elevator.id.val(),
-                            elevator.floor.val()
-                        );
-                    } else if moving_status != MovingStatus::None {
-                        distance += ELEVATOR_SELF_CHECK_MS;
-                        /* info!(
-                            "[elevator {}]: moving {:?}... (distance {})",
-                            elevator.id.val(),
-                            moving_status,
-                            distance
-                        ); */
-                    }
+                    let now = elevator.now_ms();
+                    elevator
+                        .tick(Duration::from_millis(now.saturating_sub(last_check)))
+                        .await;
+                    last_check = now;
 
                     tokio::time::sleep(self_check_interval).await;
                 }
@@ -370,8 +1314,7 @@ impl Elevator {
         // Spawn a new task to execute the future
         let handle = Some(Arc::new(tokio::spawn(async move {
             tokio::select! {
-                a = recv => Some(a),
-                b = self_check => Some(b),
+                a = self_check => Some(a),
                 _ = signal.wait_signal() => None
             }
         })));
@@ -381,57 +1324,691 @@ impl Elevator {
         Ok(())
     }
 
-    pub async fn submit(&self, action: ElevatorAction) {
-        self.action_queue.lock().await.push_back(action);
+    /// Advances the car by one self check: moves it towards its target, then
+    /// once it stands at a floor picks the next stop, serving the current floor
+    /// when it is the next stop.
+    async fn tick(&self, elapsed: Duration) {
+        if let Some(code) = self.fault.load().as_deref().copied() {
+            match code {
+                FaultCode::SensorFlapping => self.flap_floor_sensor(),
+                // Without any stop left the car brakes to the next floor it can reach
+                FaultCode::DoorStuck => {
+                    if let Some(direction) = self.moving_status.load().direction() {
+                        self.drive_for(&StopSet::default(), direction, elapsed);
+                    }
+                }
+                _ => {}
+            }
+            return;
+        }
+        if self.recovering.val() {
+            self.recover(elapsed.as_secs_f64());
+            return;
+        }
+        self.update_door(self.now_ms());
+        self.exchange_passengers().await;
+
+        let mut stops = self.stops.lock().await;
+        // A fully loaded car passes hall calls by, they wait for room to free up
+        let bypass = self.is_full_load().then(|| stops.car_calls());
+        let plan = bypass.as_ref().unwrap_or(&*stops);
+
+        if let Some(direction) = self.moving_status.load().direction() {
+            if !self.drive_for(plan, direction, elapsed) {
+                return;
+            }
+        }
+
+        let current_floor = self.floor.val();
+        let Some((floor, direction)) = plan.next_stop(current_floor, self.heading()) else {
+            self.heading.set(None);
+            return;
+        };
+
+        if floor == current_floor {
+            let hall_call = stops.serve(floor, direction);
+            self.heading.set(Some(direction.into()));
+            self.car_call_pressed.set_false();
+
+            // Under firefighter control the doors only open on the door button
+            if self.mode() != OperatingMode::Firefighter {
+                self.operate_door(DoorStatus::Open);
+            }
+
+            self.inside_button(floor).unwrap().active.set_false();
+            // The hall lamp belongs to the car the call was assigned to
+            if hall_call {
+                self.outside_button(floor)
+                    .unwrap()
+                    .set_active(direction, false);
+                self.metrics
+                    .hall_call_answered(floor, direction, self.now_ms());
+            }
+            return;
+        }
+
+        let direction = if floor > current_floor {
+            Direction::Up
+        } else {
+            Direction::Down
+        };
+        if !self.depart(floor, direction) {
+            return;
+        }
+        info!(
+            "[elevator {}]: moving {:?} to floor {}",
+            self.id.val(),
+            direction,
+            floor
+        );
+
+        // Passengers who did not fit press the hall button again, the
+        // controller dispatches the call to whichever car suits it best
+        let button = self.outside_button(current_floor).unwrap();
+        if self.mode().accepts_hall_calls()
+            && !button.is_active(direction)
+            && self.passengers.is_waiting(current_floor, direction).await
+        {
+            self.handed_back.push(current_floor, direction);
+            info!(
+                "[elevator {}]: passengers left behind at floor {}",
+                self.id.val(),
+                current_floor
+            );
+        }
     }
 
-    pub async fn call(&self, floor: u8, direction: Direction) -> Result<(), ElevatorError> {
-        let outside_button = self.outside_button(floor)?;
-        let mut cancel = false;
+    /// Lets passengers out and in while the doors are open, boarding passengers
+    /// press the inside button of their destination.
+    async fn exchange_passengers(&self) {
+        if self.door_status.load().as_ref() != &DoorStatus::Open {
+            return;
+        }
+
+        let (id, floor, now) = (self.id.val(), self.floor.val(), self.now_ms());
+        let alighted = if self.mode() == OperatingMode::FireRecall {
+            // Recalled cars only open their doors at the recall floor
+            self.passengers.evacuate(id, now).await
+        } else {
+            self.passengers.alight(id, floor, now).await
+        };
+        for passenger in alighted {
+            self.persons.sub(1);
+            self.load_kg
+                .set((self.load_kg.val() - passenger.weight_kg).max(0.0));
+            self.emit(ElevatorEvent::PassengerAlighted {
+                elevator_id: id,
+                passenger_id: passenger.id,
+                floor,
+            });
+        }
 
-        match direction {
-            Direction::Up => {
-                if outside_button.is_max_floor() {
-                    return Err(ElevatorError::AlreadyMaxFloor);
-                }
+        if !self.mode().boards_passengers() {
+            self.check_overload();
+            return;
+        }
 
-                if outside_button.is_up() {
-                    cancel = true
+        let was_full_load = self.is_full_load();
+        let capacity = self.capacity.load();
+        let room = (
+            capacity.rated_persons.saturating_sub(self.persons.val()),
+            capacity.rated_load_kg - self.load_kg.val(),
+        );
+        let boarded = self
+            .passengers
+            .board(id, floor, self.heading(), room, now)
+            .await;
+        for passenger in &boarded {
+            self.persons.add(1);
+            self.load_kg.set(self.load_kg.val() + passenger.weight_kg);
+            self.emit(ElevatorEvent::PassengerBoarded {
+                elevator_id: id,
+                passenger_id: passenger.id,
+                floor,
+            });
+        }
+        self.check_overload();
+
+        for destination in boarded.into_iter().map(|p| p.destination) {
+            if let Err(err) = self
+                .submit(ElevatorAction::Request {
+                    floor: destination,
+                    cancel: false,
+                })
+                .await
+            {
+                warn!(
+                    "[elevator {}]: passenger cannot press the button of floor {}: {}",
+                    self.id.val(),
+                    destination,
+                    err
+                );
+            }
+        }
+
+        // A car that just filled up passes its hall calls by, they go back to
+        // the controller for another car to answer
+        if !was_full_load && self.is_full_load() {
+            for (floor, direction) in self.hand_back_hall_calls().await {
+                self.handed_back.push(floor, direction);
+            }
+        }
+    }
+
+    /// Starts moving the car to a floor, returns false while the doors are not closed.
+    ///
+    /// Open doors run out their dwell and close before the car leaves, while
+    /// the interlock keeps any door command from sneaking in as it departs.
+    fn depart(&self, floor: u8, direction: Direction) -> bool {
+        let _interlock = self.door_interlock.lock().unwrap();
+        if self.overloaded.val() {
+            return false;
+        }
+        match (*self.door_status.load().as_ref(), self.mode()) {
+            (DoorStatus::Closed, _) => {}
+            (DoorStatus::Open, OperatingMode::FireRecall) => {
+                // Recalled cars leave at once, closing slowly over the light curtain
+                self.start_nudging();
+                self.operate_door(DoorStatus::Closed);
+                return false;
+            }
+            (DoorStatus::Open, OperatingMode::Firefighter) => return false,
+            (DoorStatus::Open, OperatingMode::Independent) if self.door_obstructed.is_false() => {
+                // Held open until the next car call, no dwell left to run out
+                if self.car_call_pressed.val() {
+                    self.operate_door(DoorStatus::Closed);
                 }
+                return false;
+            }
+            (DoorStatus::Open, _) if self.door_obstructed.is_false() => {
+                let timing = self.door_timing.load();
+                let open_for = self.now_ms().saturating_sub(self.door_last_change.val());
+                if open_for >= timing.dwell_ms {
+                    self.operate_door(DoorStatus::Closed);
+                }
+                return false;
+            }
+            _ => return false,
+        }
 
-                outside_button.up.toggle()
+        self.heading.set(Some(direction.into()));
+        self.target_floor.set(Some(floor.into()));
+        self.moving_status.set(MovingStatus::from(direction).into());
+        self.metrics.trip_started(self.id.val(), self.now_ms());
+        self.emit(ElevatorEvent::CarDeparted {
+            elevator_id: self.id.val(),
+            floor: self.floor.val(),
+            direction,
+            target_floor: floor,
+        });
+        true
+    }
+
+    /// Moves the car for a while in self check steps, so a long step of the
+    /// clock drives it exactly like real time does. Returns true once it rests
+    /// at its target.
+    fn drive_for(&self, stops: &StopSet, direction: Direction, elapsed: Duration) -> bool {
+        let step = Duration::from_millis(ELEVATOR_SELF_CHECK_MS);
+        let mut remaining = elapsed;
+        loop {
+            let dt = remaining.min(step);
+            remaining -= dt;
+            if self.drive(stops, direction, dt.as_secs_f64()) {
+                return true;
             }
-            Direction::Down => {
-                if outside_button.is_min_floor() {
-                    return Err(ElevatorError::AlreadyMinFloor);
+            if remaining.is_zero() {
+                return false;
+            }
+        }
+    }
+
+    /// Time until the moving car passes the next floor or rests at its target,
+    /// in milliseconds, none while it stands still.
+    ///
+    /// Follows the motion in the same self check steps driving the car does.
+    pub fn motion_event_in(&self) -> Option<u64> {
+        if self.is_idle() {
+            return None;
+        }
+        let target = self.target_floor.load().as_deref().copied()?;
+        let profile = self.motion_profile.load();
+        let target = profile.floor_position(target);
+        let floor = self.floor.val();
+        let dt = Duration::from_millis(ELEVATOR_SELF_CHECK_MS).as_secs_f64();
+
+        let mut motion = self.motion();
+        let mut steps = 1;
+        while !motion.step(&profile, target, dt) && profile.nearest_floor(motion.position) == floor
+        {
+            steps += 1;
+        }
+        Some(steps * ELEVATOR_SELF_CHECK_MS)
+    }
+
+    /// Moves the car along the shaft, returns true once it rests at its target.
+    ///
+    /// The target is brought forward to a new stop the car can still brake for,
+    /// or to the first reachable floor when there is nothing left ahead.
+    fn drive(&self, stops: &StopSet, direction: Direction, dt: f64) -> bool {
+        let profile = self.motion_profile.load_full();
+        let mut motion = self.motion();
+
+        let target = self
+            .target_floor
+            .load()
+            .as_deref()
+            .copied()
+            .unwrap_or_default();
+        let reachable = profile.next_stoppable_floor(&motion, direction);
+        let is_before = |a: u8, b: u8| match direction {
+            Direction::Up => a < b,
+            Direction::Down => a > b,
+        };
+
+        let target = match stops.next_stop(reachable, Some(direction)) {
+            Some((floor, _)) if !is_before(floor, reachable) => {
+                if stops.contains(target) && !is_before(floor, target) {
+                    target
+                } else {
+                    floor
                 }
+            }
+            _ if is_before(reachable, target) => reachable,
+            _ => target,
+        };
+        self.target_floor.set(Some(target.into()));
 
-                if outside_button.is_down() {
-                    cancel = true
+        let arrived = motion.step(&profile, profile.floor_position(target), dt);
+        self.set_motion(motion);
+
+        let floor = if arrived {
+            target
+        } else {
+            profile.nearest_floor(motion.position)
+        };
+        if floor != self.floor.val() {
+            self.floor.set(floor);
+            if !arrived {
+                self.emit(ElevatorEvent::FloorPassed {
+                    elevator_id: self.id.val(),
+                    floor,
+                });
+            }
+            info!("[elevator {}]: passing floor {}", self.id.val(), floor);
+        }
+
+        if arrived {
+            self.target_floor.set(None);
+            self.moving_status.set(MovingStatus::None.into());
+            self.metrics.trip_finished(self.id.val(), self.now_ms());
+            self.emit(ElevatorEvent::FloorArrived {
+                elevator_id: self.id.val(),
+                floor,
+            });
+            info!("[elevator {}]: arrived floor {}", self.id.val(), floor);
+        }
+
+        arrived
+    }
+
+    /// Registers or cancels a stop, keeping the button lamps in sync.
+    ///
+    /// Submitting a stop that is already pending is merged into it. The route is
+    /// planned again on the next self check, so a cancelled target is simply
+    /// never reached.
+    pub async fn submit(&self, action: ElevatorAction) -> Result<Registration, ElevatorError> {
+        if let Some(fault) = self.fault() {
+            return Err(ElevatorError::Faulted { fault });
+        }
+        let mode = self.mode();
+        let (floor, origin, cancel) = match action {
+            ElevatorAction::Request { floor, cancel } => {
+                self.inside_button(floor)?;
+                if !cancel && !mode.accepts_car_calls() {
+                    return Err(ElevatorError::OperatingModeRejected {
+                        mode,
+                        requested: None,
+                    });
                 }
+                (floor, StopOrigin::Car, cancel)
+            }
+            ElevatorAction::Call {
+                floor,
+                direction,
+                cancel,
+            } => {
+                self.outside_button(floor)?.check_direction(direction)?;
+                if !cancel && !mode.accepts_hall_calls() {
+                    return Err(ElevatorError::OperatingModeRejected {
+                        mode,
+                        requested: None,
+                    });
+                }
+                (floor, StopOrigin::hall(direction), cancel)
+            }
+        };
 
-                outside_button.down.toggle()
+        let mut stops = self.stops.lock().await;
+        if let ElevatorAction::Call {
+            direction,
+            cancel: false,
+            ..
+        } = action
+        {
+            // A lit hall button is held by the car it was assigned to, which
+            // turns the lamp off once it serves the call
+            if !stops.is_pending(floor, origin) && self.outside_button(floor)?.is_active(direction)
+            {
+                return Err(ElevatorError::HallCallAssigned { floor, direction });
+            }
+        }
+        let registration = if cancel {
+            if !stops.remove(floor, origin) {
+                return Err(ElevatorError::NothingToCancel { floor });
+            }
+            info!(
+                "[elevator {}]: cancel {:?} stop at floor {}",
+                self.id.val(),
+                origin,
+                floor
+            );
+            Registration::Cancelled
+        } else if stops.insert(floor, origin) {
+            Registration::Registered
+        } else {
+            Registration::AlreadyRegistered
+        };
+
+        let (elevator_id, direction) = (self.id.val(), origin.direction());
+        match registration {
+            Registration::Registered => self.emit(ElevatorEvent::CallRegistered {
+                elevator_id,
+                floor,
+                direction,
+            }),
+            Registration::Cancelled => self.emit(ElevatorEvent::CallCancelled {
+                elevator_id,
+                floor,
+                direction,
+            }),
+            Registration::AlreadyRegistered => {}
+        }
+
+        let active = !cancel;
+        match action {
+            ElevatorAction::Request { .. } => {
+                if active {
+                    self.car_call_pressed.set_true();
+                }
+                self.inside_button(floor)?.active.set(active)
+            }
+            ElevatorAction::Call { direction, .. } => {
+                if cancel {
+                    self.metrics.hall_call_cancelled(floor, direction);
+                } else {
+                    self.metrics
+                        .hall_call_registered(floor, direction, self.now_ms());
+                }
+                self.outside_button(floor)?.set_active(direction, active)
             }
         }
 
-        self.submit(ElevatorAction::Request { floor, cancel }).await;
+        Ok(registration)
+    }
 
+    pub async fn call(
+        &self,
+        floor: u8,
+        direction: Direction,
+    ) -> Result<Registration, ElevatorError> {
+        self.submit(ElevatorAction::Call {
+            floor,
+            direction,
+            cancel: false,
+        })
+        .await
+    }
+
+    /// Commands the doors to open or close, they then move on over time.
+    ///
+    /// Opening an open door restarts its dwell time, reversing a moving door
+    /// only takes the time needed to undo its progress.
+    ///
+    /// Under firefighter control this presses the door button, the doors only
+    /// move on while it is held and turn back once it is released.
+    ///
+    /// Fails with [`ElevatorError::CannotControlDoor`] while the car is moving.
+    pub fn door_control(&self, status: DoorStatus) -> Result<(), ElevatorError> {
+        let _interlock = self.door_interlock.lock().unwrap();
+        if let Some(fault) = self.fault() {
+            return Err(ElevatorError::Faulted { fault });
+        }
+        if !self.is_idle() {
+            return Err(ElevatorError::CannotControlDoor);
+        }
+
+        if self.mode() == OperatingMode::Firefighter {
+            self.door_button
+                .set(Some(DoorStatus::from(status.is_opening()).into()));
+        }
+        self.operate_door(status);
         Ok(())
     }
 
-    pub fn door_control(&self, status: DoorStatus) {
-        if self.moving_status.val().as_ref() != &MovingStatus::None {
+    /// Releases the door button held under firefighter control.
+    ///
+    /// Doors still moving the way of the button turn back, so opening doors
+    /// close again and closing doors reopen.
+    pub fn door_release(&self) {
+        let _interlock = self.door_interlock.lock().unwrap();
+        let Some(pressed) = self.door_button.load().as_deref().copied() else {
             return;
+        };
+        self.door_button.set(None);
+
+        let status = *self.door_status.load().as_ref();
+        let next = match (pressed, status) {
+            (DoorStatus::Open, DoorStatus::Opening) => DoorStatus::Closing,
+            (DoorStatus::Closed, DoorStatus::Closing) => DoorStatus::Opening,
+            _ => return,
+        };
+        info!(
+            "[elevator {}]: door button released, doors {:?}",
+            self.id.val(),
+            next
+        );
+        self.reverse_door(status, next, self.now_ms());
+    }
+
+    /// Drives the door operator, the caller makes sure the car rests at a floor.
+    fn operate_door(&self, status: DoorStatus) {
+        let current = *self.door_status.load().as_ref();
+        let now = self.now_ms();
+        let next = match (status.is_opening(), current) {
+            (true, DoorStatus::Open) => {
+                self.door_last_change.set(now);
+                return;
+            }
+            (true, DoorStatus::Opening) | (false, DoorStatus::Closing | DoorStatus::Closed) => {
+                return
+            }
+            (true, _) => DoorStatus::Opening,
+            (false, _) => DoorStatus::Closing,
+        };
+
+        self.reverse_door(current, next, now);
+    }
+
+    /// Asserts or clears the light curtain of the doorway.
+    ///
+    /// Closing doors reopen on an obstruction and open doors hold as long as it
+    /// lasts. After too many reopens the doors start nudging, closing slowly
+    /// whatever the light curtain says.
+    pub fn set_door_obstruction(&self, obstructed: bool) {
+        if obstructed != self.door_obstructed.val() {
+            self.door_obstructed.set(obstructed);
+            self.emit(ElevatorEvent::DoorObstructed {
+                elevator_id: self.id.val(),
+                obstructed,
+            });
+        }
+        self.update_door(self.now_ms());
+    }
+
+    /// Turns the doors around halfway through their motion.
+    fn reverse_door(&self, current: DoorStatus, next: DoorStatus, now: u64) {
+        let elapsed = now.saturating_sub(self.door_last_change.val());
+        let reversed =
+            self.door_timing
+                .load()
+                .reversed_elapsed(current, elapsed, self.door_nudging.val());
+        self.set_door_status(next, now.saturating_sub(reversed));
+    }
+
+    /// Returns true if the operating mode keeps the doors as they are.
+    ///
+    /// Recalled cars and cars in independent service keep their doors open
+    /// until they leave, under firefighter control open or closed doors never
+    /// move by themselves.
+    fn is_door_held(&self, status: DoorStatus) -> bool {
+        matches!(
+            (self.mode(), status),
+            (
+                OperatingMode::FireRecall | OperatingMode::Independent,
+                DoorStatus::Open
+            ) | (
+                OperatingMode::Firefighter,
+                DoorStatus::Open | DoorStatus::Closed
+            )
+        )
+    }
+
+    /// Keeps the doors open while the car is overloaded, returns true if they were held.
+    fn check_door_overload(&self, status: DoorStatus, now: u64) -> bool {
+        if self.overloaded.is_false() {
+            return false;
+        }
+
+        match status {
+            DoorStatus::Open => {
+                self.door_last_change.set(now);
+                true
+            }
+            DoorStatus::Closing => {
+                self.reverse_door(status, DoorStatus::Opening, now);
+                true
+            }
+            DoorStatus::Closed => {
+                // Let people out of a car standing with closed doors
+                let _interlock = self.door_interlock.lock().unwrap();
+                if self.is_idle() {
+                    self.operate_door(DoorStatus::Open);
+                }
+                true
+            }
+            DoorStatus::Opening => false,
         }
+    }
 
+    /// Closes the doors slowly from now on, ignoring the light curtain.
+    fn start_nudging(&self) {
+        if self.door_nudging.val() {
+            return;
+        }
+        self.door_nudging.set_true();
+        self.emit(ElevatorEvent::DoorNudging {
+            elevator_id: self.id.val(),
+        });
+    }
+
+    /// Reacts to an obstruction of the doorway, returns true if the doors were held.
+    fn check_door_obstruction(&self, status: DoorStatus, now: u64) -> bool {
+        if self.door_obstructed.is_false() || self.door_nudging.val() {
+            return false;
+        }
+
+        match status {
+            DoorStatus::Open => {
+                // Extend the dwell, the full time starts over once the doorway clears
+                self.door_last_change.set(now);
+                true
+            }
+            DoorStatus::Closing => {
+                let timing = self.door_timing.load();
+                if self.door_reopens.val() >= timing.max_reopens {
+                    self.start_nudging();
+                    warn!(
+                        "[elevator {}]: doors obstructed {} times, nudging",
+                        self.id.val(),
+                        self.door_reopens.val()
+                    );
+                    let elapsed = now.saturating_sub(self.door_last_change.val());
+                    let progress = elapsed * timing.nudging_close_ms / timing.close_ms.max(1);
+                    self.door_last_change.set(now.saturating_sub(progress));
+                    return false;
+                }
+
+                self.door_reopens.add(1);
+                info!("[elevator {}]: doors obstructed, reopening", self.id.val());
+                self.reverse_door(status, DoorStatus::Opening, now);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Moves the doors on once they spent their time in the current status.
+    fn update_door(&self, now: u64) {
+        if self.is_faulted() {
+            return;
+        }
+        let status = *self.door_status.load().as_ref();
+        if self.is_door_held(status)
+            || self.check_door_overload(status, now)
+            || self.check_door_obstruction(status, now)
+        {
+            return;
+        }
+
+        let Some(duration) = self
+            .door_timing
+            .load()
+            .duration(status, self.door_nudging.val())
+        else {
+            return;
+        };
+        if now.saturating_sub(self.door_last_change.val()) < duration {
+            return;
+        }
+
+        let next = match status {
+            DoorStatus::Opening => DoorStatus::Open,
+            DoorStatus::Open => DoorStatus::Closing,
+            DoorStatus::Closing | DoorStatus::Closed => DoorStatus::Closed,
+        };
+        self.set_door_status(next, now);
+    }
+
+    fn set_door_status(&self, status: DoorStatus, since: u64) {
+        if status == DoorStatus::Closed {
+            self.door_reopens.set(0);
+            self.door_nudging.set_false();
+        }
+        let previous = *self.door_status.load().as_ref();
         self.door_status.set(status.into());
+        self.door_last_change.set(since);
+        info!("[elevator {}]: doors {:?}", self.id.val(), status);
 
-        if status == DoorStatus::Open {
-            self.door_last_open.set(timestamp());
-            info!("[elevator {}]: open doors", self.id.val());
-        } else {
-            info!("[elevator {}]: close doors", self.id.val());
+        if status == previous {
+            return;
         }
+        let (elevator_id, floor) = (self.id.val(), self.floor.val());
+        self.emit(match status {
+            DoorStatus::Opening => ElevatorEvent::DoorOpening { elevator_id, floor },
+            DoorStatus::Open => ElevatorEvent::DoorOpened { elevator_id, floor },
+            DoorStatus::Closing => ElevatorEvent::DoorClosing { elevator_id, floor },
+            DoorStatus::Closed => ElevatorEvent::DoorClosed { elevator_id, floor },
+        });
     }
 
     pub fn stop(&self) -> Result<(), ElevatorError> {
@@ -506,6 +2083,41 @@ impl OutSideButton {
         }
     }
 
+    /// Creates one hall button panel for every floor.
+    pub fn for_floors(num_floors: u8) -> BTreeMap<u8, OutSideButton> {
+        let top_floor = num_floors - 1;
+        (0..num_floors)
+            .map(|floor| (floor, OutSideButton::new(floor, top_floor)))
+            .collect()
+    }
+
+    /// Checks that a hall call in the direction can be made from this floor.
+    pub fn check_direction(&self, direction: Direction) -> Result<(), ElevatorError> {
+        match direction {
+            Direction::Up if self.is_max_floor() => Err(ElevatorError::AlreadyMaxFloor {
+                floor: self.floor.val(),
+            }),
+            Direction::Down if self.is_min_floor() => Err(ElevatorError::AlreadyMinFloor {
+                floor: self.floor.val(),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn is_active(&self, direction: Direction) -> bool {
+        match direction {
+            Direction::Up => self.is_up(),
+            Direction::Down => self.is_down(),
+        }
+    }
+
+    pub fn set_active(&self, direction: Direction, active: bool) {
+        match direction {
+            Direction::Up => self.up.set(active),
+            Direction::Down => self.down.set(active),
+        }
+    }
+
     pub fn is_up(&self) -> bool {
         self.up.val()
     }
@@ -3,14 +3,21 @@ extern crate tracing;
 
 pub mod elevator_api {
     pub mod constants;
+    pub mod error;
     pub mod extrators;
     pub mod routes;
+    pub mod sse;
     pub mod utils;
+    pub mod v2;
+    pub mod ws;
 }
 
 use crate::elevator_api::constants::{DISPLAY_OFFSET, INIT_FLOOR, NUM_ELEVATORS, NUM_FLOORS};
 
-use axum::{routing::get, Extension, Router};
+use axum::{
+    routing::{get, post},
+    Extension, Router,
+};
 use std::{net::SocketAddr, sync::Arc};
 use tracing_subscriber::FmtSubscriber;
 
@@ -31,14 +38,31 @@ async fn demo() -> Result<(), Box<dyn std::error::Error>> {
         building.controller.elevators.len(),
     );
 
-    controller.start_all_elevators().await;
+    controller.start_all_elevators().await?;
+    let state_feed = elevator_api::ws::StateFeed::spawn(building.clone());
 
     let app = Router::new()
         .route("/", get(elevator_api::routes::root))
         .route("/data", get(elevator_api::utils::data))
+        .route("/ws", get(elevator_api::ws::ws))
+        .route("/events", get(elevator_api::sse::events))
         .route("/req", get(elevator_api::routes::request))
         .route("/door", get(elevator_api::routes::door_control))
-        .layer(Extension(building.clone()));
+        .route("/door_release", get(elevator_api::routes::door_release))
+        .route("/obstruct", get(elevator_api::routes::door_obstruction))
+        .route("/stats", get(elevator_api::routes::stats))
+        .route("/fire_alarm", post(elevator_api::routes::fire_alarm))
+        .route("/firefighter", post(elevator_api::routes::firefighter))
+        .route("/admin/mode", post(elevator_api::routes::operating_mode))
+        .route(
+            "/admin/emergency_stop",
+            post(elevator_api::routes::emergency_stop),
+        )
+        .route("/admin/reset", post(elevator_api::routes::reset_fault))
+        .route("/admin/fault", post(elevator_api::routes::inject_fault))
+        .nest("/api/v2", elevator_api::v2::router())
+        .layer(Extension(building.clone()))
+        .layer(Extension(state_feed));
 
     let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
     info!("please visit: http://localhost:3000");
@@ -66,11 +90,33 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 #[cfg(test)]
 mod test {
     use crate::elevator_api::constants::{DISPLAY_OFFSET, INIT_FLOOR, NUM_ELEVATORS, NUM_FLOORS};
+    use crate::elevator_api::{
+        sse::EventStream,
+        ws::{diff, StateFeed},
+    };
+    use elevator_core::utils::{
+        atomic::AtomicValue, clock::Clock, constants::HALL_CALL_WAIT_SAMPLES_LEN,
+    };
     use elevator_core::{
-        components::{enums::Direction, model::Building},
-        ElevatorController,
+        components::{
+            dispatch::{Call, DispatchStrategy, NearestCar, RoundRobin},
+            door::DoorTiming,
+            enums::{Direction, DoorStatus, OperatingMode},
+            error::{ElevatorControllerError, ElevatorError},
+            event::{ElevatorEvent, EventBus, EventRecord},
+            fault::FaultCode,
+            load::CarCapacity,
+            metrics::{MetricsCollector, TimeStats},
+            model::Building,
+            motion::{MotionProfile, MotionState},
+            passenger::PassengerStatus,
+            schedule::{StopOrigin, StopSet},
+            simulation::{Arrival, Simulation},
+            traffic::{TrafficGenerator, TrafficPattern},
+        },
+        ElevatorAction, ElevatorController, Registration,
     };
-    use std::sync::Arc;
+    use std::{sync::Arc, time::Duration};
 
     #[tokio::test]
     async fn test_1() {
@@ -88,7 +134,7 @@ mod test {
             building.controller.elevators.len(),
         );
 
-        controller.start_all_elevators().await;
+        controller.start_all_elevators().await.unwrap();
 
         let floor_0 = building.get_floor(0).unwrap();
         trace!("floor 0: {floor_0:?}");
@@ -101,9 +147,2108 @@ mod test {
 
         // not change due to its already min floor
         assert!(floor_0.call_elevator(0, Direction::Down).await.is_err());
-        assert!(!floor_0.get_outside_button(0).unwrap().is_down());
+        assert!(!floor_0.get_outside_button().unwrap().is_down());
 
         floor_0.call_elevator(0, Direction::Up).await.unwrap();
-        assert!(floor_0.get_outside_button(0).unwrap().is_up());
+        assert!(floor_0.get_outside_button().unwrap().is_up());
+    }
+
+    #[tokio::test]
+    async fn test_clock_mismatch() {
+        // a real-time controller drives itself, a manual one is only stepped
+        let controller = Arc::new(ElevatorController::new(0, 5, 0, 1));
+        controller.start_all_elevators().await.unwrap();
+        assert!(matches!(
+            controller.step(Duration::from_millis(20)).await,
+            Err(ElevatorControllerError::RealtimeClock)
+        ));
+        controller.stop_all_elevators().await.unwrap();
+
+        let manual = Arc::new(ElevatorController::new(0, 5, 0, 1).with_clock(Clock::manual()));
+        assert!(matches!(
+            manual.start_all_elevators().await,
+            Err(ElevatorControllerError::ElevatorError(
+                ElevatorError::ManualClock
+            ))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_hall_call_dispatch() {
+        let controller = Arc::new(ElevatorController::new(0, 10, 0, 2));
+        let building = Building::new(10, controller.clone());
+
+        // elevator 0 is busy going to the top floor, elevator 1 stays idle at floor 0
+        controller
+            .get_elevator(0)
+            .unwrap()
+            .submit(ElevatorAction::Request {
+                floor: 9,
+                cancel: false,
+            })
+            .await
+            .unwrap();
+
+        // on the way of elevator 0, but elevator 1 is closer
+        let elevator_0 = controller.get_elevator(0).unwrap();
+        let elevator_1 = controller.get_elevator(1).unwrap();
+        let snapshot_0 = elevator_0.snapshot().await;
+        let snapshot_1 = elevator_1.snapshot().await;
+        assert!(
+            snapshot_1.route_cost(5, Direction::Down) < snapshot_0.route_cost(5, Direction::Down)
+        );
+        assert_eq!(snapshot_0.route_cost(5, Direction::Up), 5);
+
+        // lamps are shared by every car of the floor
+        let floor_3 = building.get_floor(3).unwrap();
+        floor_3.hall_call(Direction::Up).await.unwrap();
+        assert!(floor_3.get_outside_button().unwrap().is_up());
+        assert!(elevator_0.outside_button(3).unwrap().is_up());
+        assert!(elevator_1.outside_button(3).unwrap().is_up());
+
+        assert!(building
+            .get_floor(9)
+            .unwrap()
+            .hall_call(Direction::Up)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_strategy() {
+        let controller = ElevatorController::new(0, 10, 0, 3).with_strategy(RoundRobin::default());
+        assert_eq!(controller.strategy().name(), "round_robin");
+
+        let snapshot = controller.snapshot().await;
+        let assigned = (0..4)
+            .map(|_| {
+                controller
+                    .strategy()
+                    .assign(
+                        &Call::Hall {
+                            floor: 4,
+                            direction: Direction::Up,
+                        },
+                        &snapshot,
+                    )
+                    .unwrap()
+            })
+            .collect::<Vec<u8>>();
+        assert_eq!(assigned, vec![0, 1, 2, 0]);
+
+        // car calls always stay with their own car
+        assert_eq!(
+            NearestCar.assign(
+                &Call::Car {
+                    elevator_id: 2,
+                    floor: 9
+                },
+                &snapshot
+            ),
+            Some(2)
+        );
+        assert_eq!(
+            NearestCar.assign(
+                &Call::Car {
+                    elevator_id: 7,
+                    floor: 9
+                },
+                &snapshot
+            ),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_collective_scheduling() {
+        let controller = ElevatorController::new(0, 10, 0, 1);
+        let elevator = controller.get_elevator(0).unwrap();
+
+        // floor 1 is served on the way up instead of after floor 4
+        for floor in [4, 1] {
+            controller
+                .request_elevator(
+                    0,
+                    ElevatorAction::Request {
+                        floor,
+                        cancel: false,
+                    },
+                )
+                .await
+                .unwrap();
+        }
+        assert_eq!(elevator.snapshot().await.targets, vec![1, 4]);
+
+        // going up from floor 5: car calls above, then down calls while coming
+        // back, turning at the lowest up call
+        let mut stops = StopSet::default();
+        stops.insert(8, StopOrigin::Car);
+        stops.insert(3, StopOrigin::Car);
+        stops.insert(7, StopOrigin::HallDown);
+        stops.insert(2, StopOrigin::HallUp);
+        assert_eq!(stops.route(5, Some(Direction::Up)), vec![8, 7, 3, 2]);
+        assert_eq!(
+            stops.next_stop(5, Some(Direction::Down)),
+            Some((3, Direction::Down))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cancel_request() {
+        let controller = ElevatorController::new(0, 10, 0, 1);
+        let elevator = controller.get_elevator(0).unwrap();
+
+        let request = |floor, cancel| ElevatorAction::Request { floor, cancel };
+        controller
+            .request_elevator(0, request(4, false))
+            .await
+            .unwrap();
+        controller
+            .request_elevator(0, request(6, false))
+            .await
+            .unwrap();
+        controller
+            .request_elevator(0, request(4, true))
+            .await
+            .unwrap();
+        assert!(!elevator.inside_button(4).unwrap().active.val());
+        assert_eq!(elevator.snapshot().await.targets, vec![6]);
+
+        // nothing left to cancel
+        assert!(controller
+            .request_elevator(0, request(4, true))
+            .await
+            .is_err());
+
+        controller.hall_call(3, Direction::Up).await.unwrap();
+        assert_eq!(elevator.snapshot().await.targets, vec![3, 6]);
+        controller.cancel_hall_call(3, Direction::Up).await.unwrap();
+        assert!(!controller.get_outside_button(3).unwrap().is_up());
+        assert_eq!(elevator.snapshot().await.targets, vec![6]);
+        assert!(controller.cancel_hall_call(3, Direction::Up).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_merge_repeated_requests() {
+        let controller = ElevatorController::new(0, 10, 0, 2);
+        let elevator = controller.get_elevator(0).unwrap();
+        let request = ElevatorAction::Request {
+            floor: 5,
+            cancel: false,
+        };
+
+        assert_eq!(
+            controller
+                .request_elevator(0, request.clone())
+                .await
+                .unwrap(),
+            Registration::Registered
+        );
+        for _ in 0..4 {
+            assert_eq!(
+                elevator.submit(request.clone()).await.unwrap(),
+                Registration::AlreadyRegistered
+            );
+        }
+
+        // a hall call at the same floor is a different stop
+        assert_eq!(
+            elevator.call(5, Direction::Up).await.unwrap(),
+            Registration::Registered
+        );
+        assert_eq!(
+            elevator.call(5, Direction::Up).await.unwrap(),
+            Registration::AlreadyRegistered
+        );
+        assert_eq!(
+            controller.hall_call(5, Direction::Up).await.unwrap(),
+            Registration::AlreadyRegistered
+        );
+        assert_eq!(elevator.snapshot().await.targets, vec![5]);
+    }
+
+    #[tokio::test]
+    async fn test_motion_profile() {
+        let profile = MotionProfile::uniform(10, 3.5);
+        let step = Duration::from_millis(20);
+
+        // longer trips are faster per floor once the car reaches its rated speed
+        let one_floor = profile.travel_time(0, 1, step).unwrap();
+        let nine_floors = profile.travel_time(0, 9, step).unwrap();
+        assert!(one_floor > Duration::from_secs(2));
+        assert!(nine_floors < one_floor * 9);
+        assert_eq!(profile.travel_time(9, 0, step), Some(nine_floors));
+        assert_eq!(profile.travel_time(0, 9, Duration::ZERO), None);
+
+        let mut motion = MotionState::at(0.0);
+        let mut max_speed: f64 = 0.0;
+        while !motion.step(&profile, profile.floor_position(4), 0.02) {
+            max_speed = max_speed.max(motion.velocity);
+            assert!(motion.position <= profile.floor_position(4));
+        }
+        assert_eq!(motion, MotionState::at(14.0));
+        assert_eq!(max_speed, profile.rated_speed);
+
+        // one height per floor is required
+        assert!(ElevatorController::new(0, 10, 0, 2)
+            .with_motion_profile(MotionProfile::uniform(5, 3.0))
+            .is_err());
+        let controller = ElevatorController::new(0, 10, 2, 2)
+            .with_motion_profile(profile)
+            .unwrap();
+        assert_eq!(controller.get_elevator(1).unwrap().position.val(), 7.0);
+    }
+
+    #[tokio::test]
+    async fn test_door_cycle() {
+        let controller = ElevatorController::new(0, 5, 0, 1)
+            .with_clock(Clock::manual())
+            .with_door_timing(DoorTiming {
+                open_ms: 100,
+                close_ms: 100,
+                dwell_ms: 200,
+                ..Default::default()
+            });
+        let elevator = controller.get_elevator(0).unwrap();
+        let door_status = || *elevator.door_status.load().as_ref();
+        let request = |floor| ElevatorAction::Request {
+            floor,
+            cancel: false,
+        };
+
+        controller.request_elevator(0, request(0)).await.unwrap();
+        controller.advance(Duration::from_millis(60)).await.unwrap();
+        assert_eq!(door_status(), DoorStatus::Opening);
+        controller
+            .advance(Duration::from_millis(100))
+            .await
+            .unwrap();
+        assert_eq!(door_status(), DoorStatus::Open);
+
+        // the car waits for the doors to close before leaving
+        controller.request_elevator(0, request(3)).await.unwrap();
+        controller
+            .advance(Duration::from_millis(200))
+            .await
+            .unwrap();
+        assert_eq!(door_status(), DoorStatus::Closing);
+        assert!(elevator.is_idle());
+
+        // calling the car at its floor reopens the closing doors
+        controller.hall_call(0, Direction::Up).await.unwrap();
+        controller.advance(Duration::from_millis(40)).await.unwrap();
+        assert!(door_status().is_opening());
+        assert!(elevator.is_idle());
+
+        controller.advance(Duration::from_secs(10)).await.unwrap();
+        assert_eq!(elevator.floor.val(), 3);
+        assert_eq!(controller.clock().now_ms(), 10_400);
+    }
+
+    #[tokio::test]
+    async fn test_door_obstruction() {
+        let controller = ElevatorController::new(0, 5, 0, 1)
+            .with_clock(Clock::manual())
+            .with_door_timing(DoorTiming {
+                open_ms: 100,
+                close_ms: 200,
+                dwell_ms: 200,
+                nudging_close_ms: 600,
+                max_reopens: 1,
+            });
+        let elevator = controller.get_elevator(0).unwrap();
+        let door_status = || *elevator.door_status.load().as_ref();
+
+        controller
+            .request_elevator(
+                0,
+                ElevatorAction::Request {
+                    floor: 0,
+                    cancel: false,
+                },
+            )
+            .await
+            .unwrap();
+        controller
+            .advance(Duration::from_millis(160))
+            .await
+            .unwrap();
+        assert_eq!(door_status(), DoorStatus::Open);
+
+        // the doors hold open as long as the light curtain is interrupted
+        controller.door_obstruction(0, true).unwrap();
+        controller
+            .advance(Duration::from_millis(400))
+            .await
+            .unwrap();
+        assert_eq!(door_status(), DoorStatus::Open);
+        controller.door_obstruction(0, false).unwrap();
+        controller
+            .advance(Duration::from_millis(300))
+            .await
+            .unwrap();
+        assert_eq!(door_status(), DoorStatus::Closing);
+
+        // an obstruction reopens the closing doors
+        controller.door_obstruction(0, true).unwrap();
+        assert_eq!(door_status(), DoorStatus::Opening);
+        controller.door_obstruction(0, false).unwrap();
+        controller
+            .advance(Duration::from_millis(350))
+            .await
+            .unwrap();
+        assert_eq!(door_status(), DoorStatus::Closing);
+
+        // out of retries, the doors close slowly whatever the light curtain says
+        controller.door_obstruction(0, true).unwrap();
+        assert_eq!(door_status(), DoorStatus::Closing);
+        assert!(elevator.door_nudging.val());
+        let nudging = controller
+            .events
+            .since(0)
+            .into_iter()
+            .filter(|record| record.event == ElevatorEvent::DoorNudging { elevator_id: 0 })
+            .count();
+        assert_eq!(nudging, 1);
+        controller
+            .advance(Duration::from_millis(100))
+            .await
+            .unwrap();
+        assert_eq!(door_status(), DoorStatus::Closing);
+        controller
+            .advance(Duration::from_millis(500))
+            .await
+            .unwrap();
+        assert_eq!(door_status(), DoorStatus::Closed);
+        assert!(!elevator.door_nudging.val());
+    }
+
+    #[tokio::test]
+    async fn test_door_interlock() {
+        let controller = ElevatorController::new(0, 5, 0, 1)
+            .with_clock(Clock::manual())
+            .with_door_timing(DoorTiming {
+                open_ms: 50,
+                close_ms: 50,
+                dwell_ms: 50,
+                ..Default::default()
+            });
+        let elevator = controller.get_elevator(0).unwrap();
+
+        controller.door_control(0, DoorStatus::Open).unwrap();
+        controller
+            .request_elevator(
+                0,
+                ElevatorAction::Request {
+                    floor: 4,
+                    cancel: false,
+                },
+            )
+            .await
+            .unwrap();
+
+        // the car only leaves once the doors are closed
+        controller
+            .advance(Duration::from_millis(100))
+            .await
+            .unwrap();
+        assert!(elevator.is_idle());
+        controller
+            .advance(Duration::from_millis(500))
+            .await
+            .unwrap();
+        assert!(!elevator.is_idle());
+        assert!(elevator.is_door_closed());
+
+        assert!(matches!(
+            controller.door_control(0, DoorStatus::Open),
+            Err(ElevatorControllerError::ElevatorError(
+                ElevatorError::CannotControlDoor
+            ))
+        ));
+        assert!(elevator.is_door_closed());
+    }
+
+    #[tokio::test]
+    async fn test_simulation() {
+        let arrivals = vec![
+            Arrival {
+                at_ms: 0,
+                origin: 0,
+                destination: 7,
+            },
+            Arrival {
+                at_ms: 1_000,
+                origin: 0,
+                destination: 3,
+            },
+            Arrival {
+                at_ms: 5_000,
+                origin: 9,
+                destination: 2,
+            },
+            Arrival {
+                at_ms: 5_000,
+                origin: 4,
+                destination: 1,
+            },
+            Arrival {
+                at_ms: 600_000,
+                origin: 6,
+                destination: 0,
+            },
+        ];
+        let run = || async {
+            Simulation::new(ElevatorController::new(0, 10, 0, 2))
+                .run(arrivals.clone())
+                .await
+                .unwrap()
+        };
+
+        let report = run().await;
+        assert_eq!(report.completed(), arrivals.len());
+        assert!(report.elapsed_ms > 600_000);
+        for passenger in &report.passengers {
+            assert!(passenger.wait_ms().unwrap() < passenger.journey_ms().unwrap());
+        }
+
+        // the same arrivals always give the same results
+        assert_eq!(run().await, report);
+
+        assert!(Simulation::new(ElevatorController::new(0, 10, 0, 2))
+            .run([Arrival {
+                at_ms: 0,
+                origin: 3,
+                destination: 3,
+            }])
+            .await
+            .is_err());
+
+        // a travelling car is stepped straight to the next floor it passes
+        let controller = ElevatorController::new(0, 10, 0, 1).with_clock(Clock::manual());
+        let elevator = controller.get_elevator(0).unwrap();
+        controller
+            .request_elevator(
+                0,
+                ElevatorAction::Request {
+                    floor: 9,
+                    cancel: false,
+                },
+            )
+            .await
+            .unwrap();
+        while elevator.is_idle() {
+            controller.step(Duration::from_millis(20)).await.unwrap();
+        }
+        for floor in 1..=9 {
+            let event_in = elevator.motion_event_in().unwrap();
+            assert!(event_in > 20);
+            controller
+                .step(Duration::from_millis(event_in - 20))
+                .await
+                .unwrap();
+            assert_eq!(elevator.floor.val(), floor - 1);
+            controller.step(Duration::from_millis(20)).await.unwrap();
+            assert_eq!(elevator.floor.val(), floor);
+        }
+        // then to its arrival once the floor is the nearest
+        let event_in = elevator.motion_event_in().unwrap();
+        controller
+            .step(Duration::from_millis(event_in))
+            .await
+            .unwrap();
+        assert!(elevator.is_idle());
+        assert_eq!(elevator.motion_event_in(), None);
+    }
+
+    #[tokio::test]
+    async fn test_passenger() {
+        let controller = ElevatorController::new(0, 10, 0, 1).with_clock(Clock::manual());
+        let elevator = controller.get_elevator(0).unwrap();
+        controller
+            .request_elevator(
+                0,
+                ElevatorAction::Request {
+                    floor: 9,
+                    cancel: false,
+                },
+            )
+            .await
+            .unwrap();
+        controller.advance(Duration::from_secs(5)).await.unwrap();
+
+        // the car passes by going up, the passenger waits for it to come back down
+        let passenger = controller.add_passenger(6, 2).await.unwrap();
+        assert!(controller.get_outside_button(6).unwrap().is_down());
+        controller.advance(Duration::from_secs(15)).await.unwrap();
+        assert_eq!(elevator.floor.val(), 9);
+        let waiting = controller.passengers.get(passenger.id).await.unwrap();
+        assert_eq!(waiting.status, PassengerStatus::Waiting);
+
+        controller.advance(Duration::from_secs(11)).await.unwrap();
+        let riding = controller.passengers.get(passenger.id).await.unwrap();
+        assert_eq!(riding.status, PassengerStatus::Riding);
+        assert!(elevator.inside_button(2).unwrap().active.val());
+
+        controller.advance(Duration::from_secs(30)).await.unwrap();
+        let arrived = controller.passengers.get(passenger.id).await.unwrap();
+        assert_eq!(arrived.status, PassengerStatus::Arrived);
+        assert_eq!(arrived.elevator_id, Some(0));
+        assert!(arrived.wait_ms().unwrap() < arrived.journey_ms().unwrap());
+        assert!(controller.passengers.is_all_arrived().await);
+
+        assert!(controller.add_passenger(3, 3).await.is_err());
+        assert!(controller.add_passenger(3, 10).await.is_err());
+
+        controller.passengers.prune_arrived(0).await;
+        assert!(controller.passengers.get(passenger.id).await.is_none());
+
+        // riders of a recalled car all get off at the recall floor
+        let passenger = controller.add_passenger(2, 8).await.unwrap();
+        controller.advance(Duration::from_secs(8)).await.unwrap();
+        assert!(elevator.is_moving_up());
+        assert_eq!(
+            controller
+                .passengers
+                .get(passenger.id)
+                .await
+                .unwrap()
+                .status,
+            PassengerStatus::Riding
+        );
+        controller.set_fire_alarm(true).await.unwrap();
+        controller.advance(Duration::from_secs(30)).await.unwrap();
+        assert_eq!(elevator.floor.val(), 0);
+        assert_eq!(
+            controller
+                .passengers
+                .get(passenger.id)
+                .await
+                .unwrap()
+                .status,
+            PassengerStatus::Arrived
+        );
+        assert_eq!(elevator.persons.val(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_traffic_generator() {
+        let generate = |pattern| {
+            TrafficGenerator::new(pattern, 12, 60.0, 7).generate(Duration::from_secs(3600))
+        };
+
+        // about 60 passengers every 5 minutes, the same every time for a seed
+        let up_peak = generate(TrafficPattern::UpPeak);
+        assert!((600..840).contains(&up_peak.len()));
+        assert_eq!(up_peak, generate(TrafficPattern::UpPeak));
+        assert_ne!(
+            up_peak,
+            TrafficGenerator::new(TrafficPattern::UpPeak, 12, 60.0, 8)
+                .generate(Duration::from_secs(3600))
+        );
+        assert!(up_peak.windows(2).all(|w| w[0].at_ms <= w[1].at_ms));
+        assert!(up_peak
+            .iter()
+            .all(|a| a.origin != a.destination && a.destination < 12));
+
+        let from_lobby = |arrivals: &[Arrival]| arrivals.iter().filter(|a| a.origin == 0).count();
+        let to_lobby =
+            |arrivals: &[Arrival]| arrivals.iter().filter(|a| a.destination == 0).count();
+        assert!(from_lobby(&up_peak) > up_peak.len() * 3 / 4);
+        let down_peak = generate(TrafficPattern::DownPeak);
+        assert!(to_lobby(&down_peak) > down_peak.len() * 3 / 4);
+        let interfloor = generate(TrafficPattern::Interfloor);
+        assert_eq!(from_lobby(&interfloor) + to_lobby(&interfloor), 0);
+
+        // fed into a simulated building, every arrival becomes a passenger
+        let controller = Arc::new(ElevatorController::new(0, 12, 0, 2).with_clock(Clock::manual()));
+        let building = Building::new(12, controller.clone());
+        let passengers = TrafficGenerator::new(TrafficPattern::Lunch, 12, 20.0, 7)
+            .feed(&building, Duration::from_secs(300))
+            .await;
+        assert!(!passengers.is_empty());
+        assert_eq!(controller.passengers.list().await.len(), passengers.len());
+
+        // consecutive windows cover the stream without a gap
+        let mut generator = TrafficGenerator::new(TrafficPattern::UpPeak, 12, 60.0, 7);
+        let mut windows = generator.generate(Duration::from_secs(1800));
+        windows.extend(generator.generate(Duration::from_secs(1800)));
+        assert_eq!(windows, up_peak);
+
+        // consecutive feeds show every arrival up on time
+        let controller = Arc::new(ElevatorController::new(0, 12, 0, 2).with_clock(Clock::manual()));
+        let building = Building::new(12, controller.clone());
+        controller.advance(Duration::from_secs(10)).await.unwrap();
+        let mut generator = TrafficGenerator::new(TrafficPattern::UpPeak, 12, 60.0, 7);
+        let mut fed = Vec::new();
+        for _ in 0..3 {
+            fed.extend(generator.feed(&building, Duration::from_secs(60)).await);
+        }
+        let stream = TrafficGenerator::new(TrafficPattern::UpPeak, 12, 60.0, 7)
+            .generate(Duration::from_secs(180));
+        assert_eq!(fed.len(), stream.len());
+        assert!(fed
+            .iter()
+            .zip(&stream)
+            .all(|(passenger, arrival)| passenger.arrived_ms == 10_000 + arrival.at_ms));
+    }
+
+    #[tokio::test]
+    async fn test_stats() {
+        let stats = TimeStats::from_samples((1..=10).rev().collect());
+        assert_eq!((stats.p50_ms, stats.p90_ms, stats.p95_ms), (5, 9, 10));
+        assert_eq!(stats.average_ms, 5.5);
+
+        // counts cover every call, the percentiles the latest ones only
+        let metrics = MetricsCollector::default();
+        let calls = HALL_CALL_WAIT_SAMPLES_LEN as u64 + 10;
+        for now in 0..calls {
+            metrics.hall_call_registered(1, Direction::Up, now);
+            metrics.hall_call_answered(1, Direction::Up, now + now.min(10));
+            metrics.trip_started(0, now);
+            metrics.trip_finished(0, now + 1);
+        }
+        let stats = metrics.stats(&[0], &[], calls + 1);
+        assert_eq!(stats.hall_calls_answered, calls as usize);
+        assert_eq!(stats.waiting_time.count, HALL_CALL_WAIT_SAMPLES_LEN);
+        assert_eq!(stats.waiting_time.p50_ms, 10);
+        assert_eq!(stats.elevators[0].trips, calls as usize);
+        assert_eq!(stats.elevators[0].travel_ms, calls);
+
+        let arrivals = TrafficGenerator::new(TrafficPattern::Lunch, 10, 30.0, 3)
+            .generate(Duration::from_secs(600));
+        let simulation = Simulation::new(ElevatorController::new(0, 10, 0, 3));
+        simulation.run(arrivals.clone()).await.unwrap();
+
+        let stats = simulation.building().controller.stats().await;
+        assert_eq!(stats.passengers_delivered, arrivals.len());
+        assert_eq!(stats.hall_calls_pending, 0);
+        assert!(stats.hall_calls_answered > 0);
+        assert!(stats.waiting_time.p50_ms <= stats.waiting_time.p90_ms);
+        assert!(stats.waiting_time.p90_ms <= stats.waiting_time.max_ms);
+        assert!(stats.passenger_waiting_time.average_ms < stats.journey_time.average_ms);
+        assert!(stats.handling_capacity > 0 && stats.handling_capacity <= arrivals.len());
+        assert_eq!(stats.elevators.len(), 3);
+        assert!(stats
+            .elevators
+            .iter()
+            .all(|car| car.trips > 0 && car.utilisation > 0.0 && car.utilisation <= 1.0));
+    }
+
+    #[tokio::test]
+    async fn test_car_load() {
+        let controller = ElevatorController::new(0, 10, 0, 2)
+            .with_clock(Clock::manual())
+            .with_capacity(CarCapacity {
+                rated_load_kg: 200.0,
+                rated_persons: 2,
+                full_load_ratio: 0.8,
+            });
+        let (elevator_0, elevator_1) = (
+            controller.get_elevator(0).unwrap(),
+            controller.get_elevator(1).unwrap(),
+        );
+
+        // a fully loaded car is passed over for hall calls
+        elevator_0.persons.set(2);
+        assert!(elevator_0.is_full_load());
+        assert_eq!(
+            controller
+                .dispatch(Call::Hall {
+                    floor: 3,
+                    direction: Direction::Up
+                })
+                .await
+                .unwrap()
+                .id
+                .val(),
+            1
+        );
+        elevator_0.persons.set(0);
+
+        // only two passengers fit, the third one waits for the next car
+        for _ in 0..3 {
+            controller.add_passenger(0, 5).await.unwrap();
+        }
+        controller.advance(Duration::from_secs(3)).await.unwrap();
+        let riding = controller
+            .passengers
+            .list()
+            .await
+            .iter()
+            .filter(|p| p.status == PassengerStatus::Riding)
+            .count();
+        assert_eq!(riding, 2);
+        assert_eq!(elevator_0.persons.val() + elevator_1.persons.val(), 2);
+        controller.advance(Duration::from_secs(60)).await.unwrap();
+        assert!(controller.passengers.is_all_arrived().await);
+        assert_eq!(elevator_0.persons.val() + elevator_1.persons.val(), 0);
+
+        // an overloaded car holds its doors open and stays put
+        controller
+            .request_elevator(
+                0,
+                ElevatorAction::Request {
+                    floor: 9,
+                    cancel: false,
+                },
+            )
+            .await
+            .unwrap();
+        elevator_0.set_load(250.0);
+        assert!(elevator_0.overloaded.val());
+        controller.advance(Duration::from_secs(20)).await.unwrap();
+        assert!(elevator_0.is_door_open());
+        assert!(elevator_0.is_idle());
+
+        elevator_0.set_load(80.0);
+        assert!(!elevator_0.overloaded.val());
+        controller.advance(Duration::from_secs(10)).await.unwrap();
+        assert!(!elevator_0.is_idle());
+    }
+
+    #[tokio::test]
+    async fn test_full_car_hands_back_hall_calls() {
+        let controller = ElevatorController::new(0, 10, 0, 2)
+            .with_clock(Clock::manual())
+            .with_capacity(CarCapacity {
+                rated_load_kg: 200.0,
+                rated_persons: 2,
+                full_load_ratio: 0.8,
+            });
+        let (elevator_0, elevator_1) = (
+            controller.get_elevator(0).unwrap(),
+            controller.get_elevator(1).unwrap(),
+        );
+        for floor in [0, 6] {
+            elevator_0
+                .submit(ElevatorAction::Call {
+                    floor,
+                    direction: Direction::Up,
+                    cancel: false,
+                })
+                .await
+                .unwrap();
+        }
+
+        // three passengers wait at floor 0, the first two fill car 0 up
+        for _ in 0..3 {
+            controller.add_passenger(0, 9).await.unwrap();
+        }
+        controller.advance(Duration::from_secs(3)).await.unwrap();
+        assert!(elevator_0.is_full_load());
+
+        // its hall call at floor 6 goes to the other car
+        assert!(!elevator_0.snapshot().await.targets.contains(&6));
+        assert!(elevator_1.snapshot().await.targets.contains(&6));
+
+        // the one left behind is dispatched once car 0 leaves, not handed back to it
+        controller.advance(Duration::from_secs(10)).await.unwrap();
+        assert!(elevator_0.floor.val() > 0);
+        let riding_1 = controller
+            .passengers
+            .list()
+            .await
+            .iter()
+            .filter(|p| p.status == PassengerStatus::Riding && p.elevator_id == Some(1))
+            .count();
+        assert_eq!(riding_1, 1);
+        assert!(!elevator_0.snapshot().await.targets.contains(&0));
+
+        controller.advance(Duration::from_secs(60)).await.unwrap();
+        assert!(controller.passengers.is_all_arrived().await);
+    }
+
+    #[tokio::test]
+    async fn test_fire_service() {
+        let controller = ElevatorController::new(0, 10, 0, 2)
+            .with_clock(Clock::manual())
+            .with_door_timing(DoorTiming {
+                open_ms: 100,
+                close_ms: 100,
+                dwell_ms: 100,
+                nudging_close_ms: 200,
+                ..Default::default()
+            })
+            .with_recall_floor(1)
+            .unwrap();
+        let building = Building::new(10, Arc::new(controller));
+        let controller = &building.controller;
+        let (elevator_0, elevator_1) = (
+            controller.get_elevator(0).unwrap(),
+            controller.get_elevator(1).unwrap(),
+        );
+
+        controller
+            .request_elevator(
+                0,
+                ElevatorAction::Request {
+                    floor: 9,
+                    cancel: false,
+                },
+            )
+            .await
+            .unwrap();
+        controller.advance(Duration::from_secs(3)).await.unwrap();
+        assert!(elevator_0.is_moving_up());
+        controller.hall_call(6, Direction::Down).await.unwrap();
+
+        // Phase I: every stop and hall call is dropped, the cars return to the recall floor
+        building.fire_alarm(true).await.unwrap();
+        assert!(controller.outside_buttons.values().all(|b| b.is_idle()));
+        assert!(!elevator_0.inside_button(9).unwrap().active.val());
+        assert!(matches!(
+            controller.hall_call(6, Direction::Down).await,
+            Err(ElevatorControllerError::NoElevatorAvailable)
+        ));
+        assert!(matches!(
+            controller
+                .request_elevator(
+                    0,
+                    ElevatorAction::Request {
+                        floor: 5,
+                        cancel: false
+                    }
+                )
+                .await,
+            Err(ElevatorControllerError::ElevatorError(
+                ElevatorError::OperatingModeRejected {
+                    mode: OperatingMode::FireRecall,
+                    requested: None
+                }
+            ))
+        ));
+
+        controller.advance(Duration::from_secs(30)).await.unwrap();
+        for elevator in [elevator_0, elevator_1] {
+            assert_eq!(elevator.mode(), OperatingMode::FireRecall);
+            assert_eq!(elevator.floor.val(), 1);
+            assert!(elevator.is_idle());
+            assert!(elevator.is_door_open());
+        }
+        assert_eq!(elevator_0.door_deadline_ms(), None);
+
+        // Phase II: the doors only move while their button is held
+        controller.firefighter_service(0, true).await.unwrap();
+        controller.door_control(0, DoorStatus::Closed).unwrap();
+        controller.advance(Duration::from_millis(60)).await.unwrap();
+        controller.door_release(0).unwrap();
+        controller
+            .advance(Duration::from_millis(200))
+            .await
+            .unwrap();
+        assert!(elevator_0.is_door_open());
+
+        controller.door_control(0, DoorStatus::Closed).unwrap();
+        controller
+            .advance(Duration::from_millis(200))
+            .await
+            .unwrap();
+        controller.door_release(0).unwrap();
+        assert!(elevator_0.is_door_closed());
+
+        controller
+            .request_elevator(
+                0,
+                ElevatorAction::Request {
+                    floor: 5,
+                    cancel: false,
+                },
+            )
+            .await
+            .unwrap();
+        controller.advance(Duration::from_secs(20)).await.unwrap();
+        assert_eq!(elevator_0.floor.val(), 5);
+        assert!(elevator_0.is_door_closed());
+        controller.door_control(0, DoorStatus::Open).unwrap();
+        controller
+            .advance(Duration::from_millis(200))
+            .await
+            .unwrap();
+        assert!(elevator_0.is_door_open());
+
+        // switching the key off recalls the car again while the alarm lasts
+        controller.firefighter_service(0, false).await.unwrap();
+        controller.advance(Duration::from_secs(20)).await.unwrap();
+        assert_eq!(elevator_0.mode(), OperatingMode::FireRecall);
+        assert_eq!(elevator_0.floor.val(), 1);
+        assert!(elevator_0.is_door_open());
+
+        building.fire_alarm(false).await.unwrap();
+        controller.advance(Duration::from_secs(1)).await.unwrap();
+        for elevator in [elevator_0, elevator_1] {
+            assert_eq!(elevator.mode(), OperatingMode::Normal);
+            assert!(elevator.is_door_closed());
+        }
+        assert!(controller.hall_call(6, Direction::Down).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_operating_mode() {
+        let controller = ElevatorController::new(0, 10, 0, 2).with_clock(Clock::manual());
+        let (elevator_0, elevator_1) = (
+            controller.get_elevator(0).unwrap(),
+            controller.get_elevator(1).unwrap(),
+        );
+
+        controller
+            .request_elevator(
+                0,
+                ElevatorAction::Request {
+                    floor: 8,
+                    cancel: false,
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            controller
+                .dispatch(Call::Hall {
+                    floor: 6,
+                    direction: Direction::Up
+                })
+                .await
+                .unwrap()
+                .id
+                .val(),
+            0
+        );
+        controller.hall_call(6, Direction::Up).await.unwrap();
+
+        // the hall call moves to the other car, the passenger inside is still delivered
+        controller
+            .set_operating_mode(0, OperatingMode::OutOfService)
+            .await
+            .unwrap();
+        assert_eq!(elevator_0.snapshot().await.targets, vec![8]);
+        assert_eq!(elevator_1.snapshot().await.targets, vec![6]);
+        assert!(controller.get_outside_button(6).unwrap().is_up());
+        assert_eq!(
+            serde_json::to_value(elevator_0.as_ref()).unwrap()["mode"],
+            "OutOfService"
+        );
+
+        assert!(matches!(
+            controller
+                .request_elevator(
+                    0,
+                    ElevatorAction::Request {
+                        floor: 2,
+                        cancel: false
+                    }
+                )
+                .await,
+            Err(ElevatorControllerError::ElevatorError(
+                ElevatorError::OperatingModeRejected {
+                    mode: OperatingMode::OutOfService,
+                    requested: None
+                }
+            ))
+        ));
+        assert_eq!(
+            controller
+                .dispatch(Call::Hall {
+                    floor: 3,
+                    direction: Direction::Down
+                })
+                .await
+                .unwrap()
+                .id
+                .val(),
+            1
+        );
+
+        controller.advance(Duration::from_secs(30)).await.unwrap();
+        assert_eq!(elevator_0.floor.val(), 8);
+        assert!(!elevator_0.has_stops().await);
+
+        // inspection drops the car calls too
+        controller
+            .request_elevator(
+                1,
+                ElevatorAction::Request {
+                    floor: 2,
+                    cancel: false,
+                },
+            )
+            .await
+            .unwrap();
+        controller
+            .set_operating_mode(1, OperatingMode::Inspection)
+            .await
+            .unwrap();
+        assert!(!elevator_1.has_stops().await);
+        assert!(!elevator_1.inside_button(2).unwrap().active.val());
+        assert!(matches!(
+            controller.hall_call(3, Direction::Down).await,
+            Err(ElevatorControllerError::NoElevatorAvailable)
+        ));
+        // fire service is entered by the alarm, the error names the refused mode
+        let err = controller
+            .set_operating_mode(1, OperatingMode::FireRecall)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ElevatorControllerError::ElevatorError(ElevatorError::OperatingModeRejected {
+                mode: OperatingMode::Inspection,
+                requested: Some(OperatingMode::FireRecall)
+            })
+        ));
+        assert_eq!(
+            err.to_string(),
+            "not available in the current operating mode Inspection, cannot switch to FireRecall"
+        );
+
+        controller
+            .set_operating_mode(0, OperatingMode::Normal)
+            .await
+            .unwrap();
+        assert!(controller.hall_call(3, Direction::Down).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_independent_service() {
+        let controller = ElevatorController::new(0, 10, 0, 2).with_clock(Clock::manual());
+        let (elevator_0, elevator_1) = (
+            controller.get_elevator(0).unwrap(),
+            controller.get_elevator(1).unwrap(),
+        );
+
+        controller
+            .set_operating_mode(0, OperatingMode::Independent)
+            .await
+            .unwrap();
+        assert!(matches!(
+            elevator_0.call(5, Direction::Up).await,
+            Err(ElevatorError::OperatingModeRejected {
+                mode: OperatingMode::Independent,
+                requested: None
+            })
+        ));
+        controller.hall_call(5, Direction::Up).await.unwrap();
+        assert_eq!(elevator_1.snapshot().await.targets, vec![5]);
+
+        // the doors stay open at the floor until the next car call
+        controller
+            .request_elevator(
+                0,
+                ElevatorAction::Request {
+                    floor: 3,
+                    cancel: false,
+                },
+            )
+            .await
+            .unwrap();
+        controller.advance(Duration::from_secs(60)).await.unwrap();
+        assert_eq!(elevator_0.floor.val(), 3);
+        assert!(elevator_0.is_door_open());
+        assert_eq!(elevator_0.door_deadline_ms(), None);
+
+        controller
+            .request_elevator(
+                0,
+                ElevatorAction::Request {
+                    floor: 1,
+                    cancel: false,
+                },
+            )
+            .await
+            .unwrap();
+        controller.advance(Duration::from_secs(5)).await.unwrap();
+        assert!(!elevator_0.is_idle());
+        assert!(elevator_0.is_door_closed());
+
+        // back in normal operation the doors close on their own again
+        controller.advance(Duration::from_secs(10)).await.unwrap();
+        assert_eq!(elevator_0.floor.val(), 1);
+        assert!(elevator_0.is_door_open());
+        controller
+            .set_operating_mode(0, OperatingMode::Normal)
+            .await
+            .unwrap();
+        controller.advance(Duration::from_secs(10)).await.unwrap();
+        assert!(elevator_0.is_door_closed());
+        assert!(elevator_0.call(5, Direction::Down).await.is_ok());
+
+        // a call queued before the car stopped does not close the doors either
+        controller
+            .set_operating_mode(0, OperatingMode::Independent)
+            .await
+            .unwrap();
+        for floor in [3, 6] {
+            controller
+                .request_elevator(
+                    0,
+                    ElevatorAction::Request {
+                        floor,
+                        cancel: false,
+                    },
+                )
+                .await
+                .unwrap();
+        }
+        controller.advance(Duration::from_secs(60)).await.unwrap();
+        assert_eq!(elevator_0.floor.val(), 3);
+        assert!(elevator_0.is_door_open());
+        controller
+            .request_elevator(
+                0,
+                ElevatorAction::Request {
+                    floor: 6,
+                    cancel: false,
+                },
+            )
+            .await
+            .unwrap();
+        controller.advance(Duration::from_secs(20)).await.unwrap();
+        assert_eq!(elevator_0.floor.val(), 6);
+    }
+
+    #[tokio::test]
+    async fn test_emergency_stop() {
+        let controller = ElevatorController::new(0, 10, 0, 2).with_clock(Clock::manual());
+        let elevator = controller.get_elevator(0).unwrap();
+
+        controller
+            .request_elevator(
+                0,
+                ElevatorAction::Request {
+                    floor: 9,
+                    cancel: false,
+                },
+            )
+            .await
+            .unwrap();
+        controller.advance(Duration::from_secs(4)).await.unwrap();
+        assert!(elevator.is_moving_up());
+
+        // the car halts mid-shaft and rejects every action
+        controller.emergency_stop(0).await.unwrap();
+        let position = elevator.position.val();
+        let profile = elevator.motion_profile.load_full();
+        let nearest = profile.nearest_floor(position);
+        assert!((profile.floor_position(nearest) - position).abs() > 0.1);
+        assert_eq!(
+            elevator.fault.load().as_deref().copied(),
+            Some(FaultCode::EmergencyStop)
+        );
+
+        controller.advance(Duration::from_secs(5)).await.unwrap();
+        assert!(elevator.is_idle());
+        assert_eq!(elevator.position.val(), position);
+        assert!(matches!(
+            controller
+                .request_elevator(
+                    0,
+                    ElevatorAction::Request {
+                        floor: 2,
+                        cancel: false
+                    }
+                )
+                .await,
+            Err(ElevatorControllerError::ElevatorError(
+                ElevatorError::Faulted {
+                    fault: FaultCode::EmergencyStop
+                }
+            ))
+        ));
+        assert!(matches!(
+            elevator.door_control(DoorStatus::Open),
+            Err(ElevatorError::Faulted {
+                fault: FaultCode::EmergencyStop
+            })
+        ));
+        assert_eq!(
+            controller
+                .dispatch(Call::Hall {
+                    floor: 3,
+                    direction: Direction::Up
+                })
+                .await
+                .unwrap()
+                .id
+                .val(),
+            1
+        );
+
+        // the reset creeps to the nearest floor and opens the doors
+        controller.reset_fault(0).unwrap();
+        controller.advance(Duration::from_secs(1)).await.unwrap();
+        assert!(elevator.recovering.val());
+        assert!(elevator.velocity.val().abs() <= 0.3);
+        while elevator.recovering.val() {
+            controller
+                .advance(Duration::from_millis(100))
+                .await
+                .unwrap();
+        }
+        assert_eq!(elevator.floor.val(), nearest);
+        assert!(elevator.door_status.load().is_opening());
+
+        // then the pending stops are served again
+        controller.advance(Duration::from_secs(30)).await.unwrap();
+        assert_eq!(elevator.floor.val(), 9);
+        assert!(matches!(
+            elevator.reset_fault(),
+            Err(ElevatorError::NotFaulted)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_fault_injection() {
+        let controller = ElevatorController::new(0, 10, 0, 2).with_clock(Clock::manual());
+        let (elevator_0, elevator_1) = (
+            controller.get_elevator(0).unwrap(),
+            controller.get_elevator(1).unwrap(),
+        );
+
+        controller
+            .request_elevator(
+                0,
+                ElevatorAction::Request {
+                    floor: 8,
+                    cancel: false,
+                },
+            )
+            .await
+            .unwrap();
+        controller.hall_call(6, Direction::Up).await.unwrap();
+        assert_eq!(elevator_0.snapshot().await.targets, vec![6, 8]);
+        controller.advance(Duration::from_secs(3)).await.unwrap();
+
+        // the hall calls of the failed car go to the other one
+        controller
+            .inject_fault(0, FaultCode::MotorFailure)
+            .await
+            .unwrap();
+        assert_eq!(elevator_0.snapshot().await.targets, vec![8]);
+        assert_eq!(elevator_1.snapshot().await.targets, vec![6]);
+        assert!(controller.get_outside_button(6).unwrap().is_up());
+        let position = elevator_0.position.val();
+        controller.advance(Duration::from_secs(5)).await.unwrap();
+        assert_eq!(elevator_0.position.val(), position);
+
+        // cancelling the call passes the failed car by
+        assert_eq!(
+            controller.cancel_hall_call(6, Direction::Up).await.unwrap(),
+            Registration::Cancelled
+        );
+        assert!(!controller.get_outside_button(6).unwrap().is_up());
+
+        // the floor reading of a flapping sensor changes on every self check
+        controller
+            .inject_fault(1, FaultCode::SensorFlapping)
+            .await
+            .unwrap();
+        let mut readings = Vec::new();
+        for _ in 0..4 {
+            controller.advance(Duration::from_millis(20)).await.unwrap();
+            readings.push(elevator_1.floor.val());
+        }
+        assert_ne!(readings[0], readings[1]);
+        assert_eq!(readings[0], readings[2]);
+        assert!(matches!(
+            controller.hall_call(3, Direction::Down).await,
+            Err(ElevatorControllerError::NoElevatorAvailable)
+        ));
+        controller.reset_fault(1).unwrap();
+
+        // a car that lost its position finds it again at the lowest floor
+        controller.reset_fault(0).unwrap();
+        controller
+            .inject_fault(0, FaultCode::PositionLost)
+            .await
+            .unwrap();
+        controller.reset_fault(0).unwrap();
+        assert!(elevator_0.is_moving_down());
+        while elevator_0.recovering.val() {
+            controller
+                .advance(Duration::from_millis(100))
+                .await
+                .unwrap();
+        }
+        assert_eq!(elevator_0.floor.val(), 0);
+
+        // stuck doors stay shut, yet the running car brakes to the next floor
+        controller
+            .request_elevator(
+                0,
+                ElevatorAction::Request {
+                    floor: 8,
+                    cancel: false,
+                },
+            )
+            .await
+            .unwrap();
+        while elevator_0.floor.val() < 2 {
+            controller
+                .advance(Duration::from_millis(100))
+                .await
+                .unwrap();
+        }
+        controller
+            .inject_fault(0, FaultCode::DoorStuck)
+            .await
+            .unwrap();
+        controller.advance(Duration::from_secs(20)).await.unwrap();
+        let floor = elevator_0.floor.val();
+        assert!(elevator_0.is_idle() && (2..8).contains(&floor));
+        assert_eq!(
+            elevator_0.position.val(),
+            elevator_0.motion_profile.load().floor_position(floor)
+        );
+        assert!(elevator_0.is_door_closed());
+
+        // a car stuck between floors stands off floor level until it recovers
+        while elevator_1.recovering.val() {
+            controller
+                .advance(Duration::from_millis(100))
+                .await
+                .unwrap();
+        }
+        let floor = elevator_1.floor.val();
+        let level = elevator_1.motion_profile.load().floor_position(floor);
+        controller
+            .inject_fault(1, FaultCode::StuckBetweenFloors)
+            .await
+            .unwrap();
+        assert!((elevator_1.position.val() - level).abs() > 1.0);
+        controller.reset_fault(1).unwrap();
+        while elevator_1.recovering.val() {
+            controller
+                .advance(Duration::from_millis(100))
+                .await
+                .unwrap();
+        }
+        assert!(
+            (elevator_1.position.val()
+                - elevator_1
+                    .motion_profile
+                    .load()
+                    .floor_position(elevator_1.floor.val()))
+            .abs()
+                < 0.01
+        );
+    }
+
+    #[tokio::test]
+    async fn test_state_changes() {
+        let controller = Arc::new(ElevatorController::new(0, 5, 0, 2).with_clock(Clock::manual()));
+        let building = Building::new(5, controller.clone());
+        let state = serde_json::to_value(&building).unwrap();
+
+        let mut changes = Vec::new();
+        diff(
+            "",
+            &state,
+            &serde_json::to_value(&building).unwrap(),
+            &mut changes,
+        );
+        assert!(changes.is_empty());
+
+        controller.hall_call(3, Direction::Up).await.unwrap();
+        let next = serde_json::to_value(&building).unwrap();
+        diff("", &state, &next, &mut changes);
+        assert!(changes.contains(&(
+            "/controller/outside_buttons/3/up".to_string(),
+            serde_json::Value::Bool(true)
+        )));
+        assert!(changes
+            .iter()
+            .all(|(path, value)| next.pointer(path) == Some(value)));
+
+        // keys are escaped as JSON pointer tokens
+        use serde_json::json;
+        let (old, new) = (json!({ "a/b": 1, "c~d": 1 }), json!({ "a/b": 2, "c~d": 2 }));
+        changes.clear();
+        diff("", &old, &new, &mut changes);
+        assert_eq!(
+            changes,
+            vec![
+                ("/a~1b".to_string(), json!(2)),
+                ("/c~0d".to_string(), json!(2))
+            ]
+        );
+        assert!(changes
+            .iter()
+            .all(|(path, value)| new.pointer(path) == Some(value)));
+    }
+
+    #[tokio::test]
+    async fn test_state_feed() {
+        let controller = Arc::new(ElevatorController::new(0, 10, 0, 1).with_clock(Clock::manual()));
+        let building = Arc::new(Building::new(10, controller.clone()));
+        let feed = StateFeed::spawn(building.clone());
+        let (snapshot, mut receiver) = feed.subscribe();
+        assert!(snapshot.contains("\"snapshot\""));
+
+        // the events of the cars are sent with the values they changed
+        controller
+            .request_elevator(
+                0,
+                ElevatorAction::Request {
+                    floor: 9,
+                    cancel: false,
+                },
+            )
+            .await
+            .unwrap();
+        let message = tokio::time::timeout(Duration::from_secs(1), receiver.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        let message: serde_json::Value = serde_json::from_str(&message).unwrap();
+        assert_eq!(message["events"][0]["type"], "CallRegistered");
+        controller.advance(Duration::from_secs(4)).await.unwrap();
+        while tokio::time::timeout(Duration::from_millis(300), receiver.recv())
+            .await
+            .is_ok()
+        {}
+
+        // a moving car is followed between events
+        let position = |message: &serde_json::Value| {
+            message["events"].as_array().unwrap().is_empty()
+                && message["changes"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .any(|change| change["path"].as_str().unwrap().ends_with("/position"))
+        };
+        controller
+            .advance(Duration::from_millis(100))
+            .await
+            .unwrap();
+        let message = tokio::time::timeout(Duration::from_secs(1), async {
+            loop {
+                let message: serde_json::Value =
+                    serde_json::from_str(&receiver.recv().await.unwrap()).unwrap();
+                if position(&message) {
+                    return message;
+                }
+            }
+        })
+        .await
+        .unwrap();
+        assert!(position(&message));
+    }
+
+    #[tokio::test]
+    async fn test_events() {
+        let bus = EventBus::new(3);
+        for floor in 0..5 {
+            bus.publish(
+                ElevatorEvent::FloorArrived {
+                    elevator_id: 0,
+                    floor,
+                },
+                0,
+            );
+        }
+        let ids = |records: Vec<EventRecord>| records.iter().map(|r| r.id).collect::<Vec<u64>>();
+        assert_eq!(ids(bus.since(0)), vec![3, 4, 5]);
+        let (replay, mut receiver) = bus.resume(4);
+        assert_eq!(ids(replay), vec![5]);
+        bus.publish(
+            ElevatorEvent::DoorOpened {
+                elevator_id: 0,
+                floor: 4,
+            },
+            0,
+        );
+        assert_eq!(receiver.recv().await.unwrap().id, 6);
+
+        // a stream only replays for a client that had events, and catches up
+        // from the history when it lags behind
+        let bus = Arc::new(bus);
+        let mut stream = EventStream::new(bus.clone(), None);
+        for floor in 0..5 {
+            bus.publish(
+                ElevatorEvent::FloorArrived {
+                    elevator_id: 0,
+                    floor,
+                },
+                0,
+            );
+        }
+        let mut received = Vec::new();
+        for _ in 0..3 {
+            received.push(stream.next().await.unwrap().id);
+        }
+        assert_eq!(received, vec![9, 10, 11]);
+        let mut resumed = EventStream::new(bus.clone(), Some(10));
+        assert_eq!(resumed.next().await.unwrap().id, 11);
+
+        let controller = ElevatorController::new(0, 5, 0, 1).with_clock(Clock::manual());
+        controller.hall_call(3, Direction::Up).await.unwrap();
+        controller.advance(Duration::from_secs(20)).await.unwrap();
+        controller
+            .request_elevator(
+                0,
+                ElevatorAction::Request {
+                    floor: 1,
+                    cancel: false,
+                },
+            )
+            .await
+            .unwrap();
+        controller
+            .request_elevator(
+                0,
+                ElevatorAction::Request {
+                    floor: 1,
+                    cancel: true,
+                },
+            )
+            .await
+            .unwrap();
+        controller.emergency_stop(0).await.unwrap();
+
+        let events = controller
+            .events
+            .since(0)
+            .into_iter()
+            .map(|record| record.event)
+            .collect::<Vec<ElevatorEvent>>();
+        assert_eq!(
+            events,
+            vec![
+                ElevatorEvent::CallRegistered {
+                    elevator_id: 0,
+                    floor: 3,
+                    direction: Some(Direction::Up)
+                },
+                ElevatorEvent::CarDeparted {
+                    elevator_id: 0,
+                    floor: 0,
+                    direction: Direction::Up,
+                    target_floor: 3
+                },
+                ElevatorEvent::FloorPassed {
+                    elevator_id: 0,
+                    floor: 1
+                },
+                ElevatorEvent::FloorPassed {
+                    elevator_id: 0,
+                    floor: 2
+                },
+                ElevatorEvent::FloorPassed {
+                    elevator_id: 0,
+                    floor: 3
+                },
+                ElevatorEvent::FloorArrived {
+                    elevator_id: 0,
+                    floor: 3
+                },
+                ElevatorEvent::DoorOpening {
+                    elevator_id: 0,
+                    floor: 3
+                },
+                ElevatorEvent::DoorOpened {
+                    elevator_id: 0,
+                    floor: 3
+                },
+                ElevatorEvent::DoorClosing {
+                    elevator_id: 0,
+                    floor: 3
+                },
+                ElevatorEvent::DoorClosed {
+                    elevator_id: 0,
+                    floor: 3
+                },
+                ElevatorEvent::CallRegistered {
+                    elevator_id: 0,
+                    floor: 1,
+                    direction: None
+                },
+                ElevatorEvent::CallCancelled {
+                    elevator_id: 0,
+                    floor: 1,
+                    direction: None
+                },
+                ElevatorEvent::Fault {
+                    elevator_id: 0,
+                    code: FaultCode::EmergencyStop
+                },
+            ]
+        );
+        assert_eq!(
+            serde_json::to_value(&controller.events.since(12)[0]).unwrap()["type"],
+            "Fault"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_subscribe() {
+        let controller = ElevatorController::new(0, 5, 0, 1).with_clock(Clock::manual());
+        let mut receiver = controller.subscribe();
+
+        controller.add_passenger(0, 2).await.unwrap();
+        controller.advance(Duration::from_secs(30)).await.unwrap();
+        let elevator = controller.get_elevator(0).unwrap();
+        elevator.set_door_obstruction(true);
+        elevator.set_door_obstruction(false);
+        elevator.set_load(5000.0);
+        elevator.set_load(0.0);
+        controller
+            .set_operating_mode(0, OperatingMode::Independent)
+            .await
+            .unwrap();
+        controller.set_fire_alarm(true).await.unwrap();
+        controller.emergency_stop(0).await.unwrap();
+        controller.reset_fault(0).unwrap();
+
+        let mut names = Vec::new();
+        while let Ok(record) = receiver.try_recv() {
+            names.push(record.event.name());
+        }
+        for name in [
+            "PassengerBoarded",
+            "PassengerAlighted",
+            "DoorObstructed",
+            "Overloaded",
+            "ModeChanged",
+            "FireAlarm",
+            "Fault",
+            "FaultReset",
+        ] {
+            assert!(names.contains(&name), "{name} missing from {names:?}");
+        }
+        assert_eq!(names.iter().filter(|&&n| n == "Overloaded").count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_api_v2() {
+        use crate::elevator_api::{
+            error::ApiError,
+            extrators::{CarCallBody, DoorBody, DoorCommand, HallCallBody, HallDirection},
+            v2,
+        };
+        use axum::{extract::Path, http::StatusCode, response::IntoResponse, Extension, Json};
+
+        let controller = Arc::new(ElevatorController::new(0, 5, 0, 2).with_clock(Clock::manual()));
+        let building = Arc::new(Building::new(5, controller.clone()));
+        let status = |response: axum::response::Response| response.status();
+
+        let car_call = |elevator_id, floor| {
+            v2::car_call(
+                Extension(building.clone()),
+                Ok(Path(elevator_id)),
+                Ok(Json(CarCallBody { floor })),
+            )
+        };
+        assert_eq!(
+            status(car_call(0, 3).await.into_response()),
+            StatusCode::CREATED
+        );
+        assert_eq!(status(car_call(0, 3).await.into_response()), StatusCode::OK);
+        let err = car_call(7, 3).await.unwrap_err();
+        assert_eq!(
+            (err.status, err.code),
+            (StatusCode::NOT_FOUND, "elevator_not_found")
+        );
+        let err = car_call(0, 9).await.unwrap_err();
+        assert_eq!(
+            (err.status, err.code),
+            (StatusCode::UNPROCESSABLE_ENTITY, "invalid_floor")
+        );
+        let response = v2::cancel_car_call(Extension(building.clone()), Ok(Path((0, 3)))).await;
+        assert_eq!(response.unwrap().registration, Registration::Cancelled);
+        let err = v2::cancel_car_call(Extension(building.clone()), Ok(Path((0, 3))))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            (err.status, err.code),
+            (StatusCode::NOT_FOUND, "nothing_to_cancel")
+        );
+
+        let hall_call = |floor, direction| {
+            v2::hall_call(
+                Extension(building.clone()),
+                Ok(Path(floor)),
+                Ok(Json(HallCallBody { direction })),
+            )
+        };
+        assert_eq!(
+            status(hall_call(2, HallDirection::Up).await.into_response()),
+            StatusCode::CREATED
+        );
+        let err = hall_call(8, HallDirection::Up).await.unwrap_err();
+        assert_eq!(
+            (err.status, err.code),
+            (StatusCode::NOT_FOUND, "floor_not_found")
+        );
+        let err = hall_call(4, HallDirection::Up).await.unwrap_err();
+        assert_eq!(err.code, "already_max_floor");
+        let response = v2::cancel_hall_call(
+            Extension(building.clone()),
+            Ok(Path((2, HallDirection::Up))),
+        )
+        .await;
+        assert_eq!(response.unwrap().registration, Registration::Cancelled);
+
+        let door = |command| {
+            v2::door(
+                Extension(building.clone()),
+                Ok(Path(1)),
+                Ok(Json(DoorBody { command })),
+            )
+        };
+        assert_eq!(door(DoorCommand::Open).await.unwrap(), StatusCode::ACCEPTED);
+        controller.emergency_stop(1).await.unwrap();
+        let err = door(DoorCommand::Close).await.unwrap_err();
+        assert_eq!((err.status, err.code), (StatusCode::CONFLICT, "faulted"));
+        assert_eq!(
+            ApiError::from(ElevatorControllerError::NoElevatorAvailable).status,
+            StatusCode::CONFLICT
+        );
+    }
+
+    #[tokio::test]
+    async fn test_api_v2_router() {
+        use crate::elevator_api::v2;
+        use axum::{
+            body::Body,
+            http::{Method, Request, StatusCode},
+            Extension,
+        };
+        use serde_json::{json, Value};
+        use tower::ServiceExt;
+
+        let controller = Arc::new(ElevatorController::new(0, 5, 0, 2).with_clock(Clock::manual()));
+        let building = Arc::new(Building::new(5, controller.clone()));
+        let app = v2::router().layer(Extension(building));
+
+        let send = |method: Method, uri: &str, body: Option<Value>| {
+            let request = Request::builder().method(method).uri(uri);
+            let request = match body {
+                Some(body) => request
+                    .header("content-type", "application/json")
+                    .body(Body::from(body.to_string())),
+                None => request.body(Body::empty()),
+            };
+            let app = app.clone();
+            async move {
+                let response = app.oneshot(request.unwrap()).await.unwrap();
+                let status = response.status();
+                let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+                (
+                    status,
+                    serde_json::from_slice::<Value>(&body).unwrap_or(Value::Null),
+                )
+            }
+        };
+        let code = |body: &Value| body["error"]["code"].clone();
+
+        let (status, body) = send(
+            Method::POST,
+            "/elevators/0/car-calls",
+            Some(json!({ "floor": 3 })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(
+            body,
+            json!({ "elevator_id": 0, "floor": 3, "direction": null, "registration": "registered" })
+        );
+        let (status, body) = send(Method::DELETE, "/elevators/0/car-calls/3", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["registration"], "cancelled");
+
+        // Hall directions are snake_case in bodies, paths and responses alike
+        let (status, body) = send(
+            Method::POST,
+            "/floors/2/hall-calls",
+            Some(json!({ "direction": "up" })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(body["direction"], "up");
+        let (status, body) = send(Method::DELETE, "/floors/2/hall-calls/up", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["direction"], "up");
+        let (status, body) = send(
+            Method::POST,
+            "/floors/2/hall-calls",
+            Some(json!({ "direction": "Up" })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(code(&body), "invalid_body");
+        let (status, body) = send(Method::DELETE, "/floors/2/hall-calls/Up", None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(code(&body), "invalid_path");
+
+        let (status, body) = send(
+            Method::PUT,
+            "/elevators/1/door",
+            Some(json!({ "command": "open" })),
+        )
+        .await;
+        assert_eq!((status, body), (StatusCode::ACCEPTED, Value::Null));
+
+        // Rejections are answered by the extractors with the v2 error body
+        let (status, body) = send(
+            Method::POST,
+            "/elevators/x/car-calls",
+            Some(json!({ "floor": 3 })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(code(&body), "invalid_path");
+        let (status, body) = send(Method::POST, "/elevators/0/car-calls", None).await;
+        assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert_eq!(code(&body), "invalid_body");
+        let (status, body) = send(
+            Method::POST,
+            "/elevators/0/car-calls",
+            Some(json!({ "level": 3 })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(code(&body), "invalid_body");
+        let (status, body) = send(
+            Method::POST,
+            "/elevators/7/car-calls",
+            Some(json!({ "floor": 3 })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(code(&body), "elevator_not_found");
+
+        let (status, _) = send(Method::GET, "/elevators/0/car-calls", None).await;
+        assert_eq!(status, StatusCode::METHOD_NOT_ALLOWED);
+        let (status, _) = send(Method::POST, "/elevators/0/hall-calls", None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_error_codes() {
+        use crate::elevator_api::error::ApiError;
+        use elevator_core::components::error::BuildingError;
+        use serde_json::json;
+
+        let controller = Arc::new(ElevatorController::new(0, 5, 0, 2).with_clock(Clock::manual()));
+        let building = Building::new(5, controller.clone());
+
+        let err = building.get_floor(7).unwrap_err();
+        assert_eq!(err.code(), "floor_not_found");
+        assert_eq!(
+            serde_json::to_value(&err).unwrap(),
+            json!({ "code": "floor_not_found", "floor": 7, "limit": 4 })
+        );
+
+        // Wrapped errors read the same as the error raised by the car
+        let err = BuildingError::from(
+            building
+                .get_floor(4)
+                .unwrap()
+                .hall_call(Direction::Up)
+                .await
+                .unwrap_err(),
+        );
+        assert_eq!(err.code(), "already_max_floor");
+        assert_eq!(err.to_string(), "already max floor 4");
+        assert_eq!(
+            serde_json::to_value(&err).unwrap(),
+            json!({ "code": "already_max_floor", "floor": 4 })
+        );
+
+        let err = BuildingError::from(
+            controller
+                .request_elevator(
+                    0,
+                    ElevatorAction::Request {
+                        floor: 9,
+                        cancel: false,
+                    },
+                )
+                .await
+                .unwrap_err(),
+        );
+        assert_eq!(
+            serde_json::to_value(&err).unwrap(),
+            json!({ "code": "invalid_floor", "floor": 9, "limit": 4 })
+        );
+        let err = controller.get_elevator(3).unwrap_err();
+        assert_eq!(
+            serde_json::to_value(&err).unwrap(),
+            json!({ "code": "elevator_not_found", "elevator_id": 3, "limit": 1 })
+        );
+
+        controller
+            .inject_fault(1, FaultCode::DoorStuck)
+            .await
+            .unwrap();
+        let err = ApiError::from(controller.door_control(1, DoorStatus::Open).unwrap_err());
+        assert_eq!(err.code, "faulted");
+        assert_eq!(err.details, json!({ "fault": "DoorStuck" }));
+        let err = ApiError::from(controller.reset_fault(0).unwrap_err());
+        assert_eq!((err.code, err.details), ("not_faulted", json!({})));
+
+        // the v1 API answers with the same code and details
+        assert_eq!(
+            crate::elevator_api::utils::fail(ElevatorError::FloorButtonNotExists {
+                floor: 9,
+                limit: 4
+            }),
+            json!({
+                "result": "fail",
+                "error": "floor button 9 not exists, the top floor is 4",
+                "code": "invalid_floor",
+                "details": { "floor": 9, "limit": 4 }
+            })
+        );
+
+        // every error serializes with the code it reports
+        use elevator_core::components::error::{FloorError, SimulationError};
+        let elevator_errors = || {
+            vec![
+                ElevatorError::RequestError,
+                ElevatorError::CannotControlDoor,
+                ElevatorError::AlreadyStarted,
+                ElevatorError::ManualClock,
+                ElevatorError::SignalNotExists,
+                ElevatorError::AlreadyMaxFloor { floor: 4 },
+                ElevatorError::AlreadyMinFloor { floor: 0 },
+                ElevatorError::FloorButtonNotExists { floor: 9, limit: 4 },
+                ElevatorError::NothingToCancel { floor: 1 },
+                ElevatorError::HallCallAssigned {
+                    floor: 1,
+                    direction: Direction::Up,
+                },
+                ElevatorError::InvalidMotionProfile,
+                ElevatorError::CannotChangeMotionProfile,
+                ElevatorError::OperatingModeRejected {
+                    mode: OperatingMode::Normal,
+                    requested: Some(OperatingMode::FireRecall),
+                },
+                ElevatorError::Faulted {
+                    fault: FaultCode::DoorStuck,
+                },
+                ElevatorError::NotFaulted,
+            ]
+        };
+        let controller_errors = || {
+            vec![
+                ElevatorControllerError::GetElevatorError {
+                    elevator_id: 3,
+                    limit: 1,
+                },
+                ElevatorControllerError::NoElevatorAvailable,
+                ElevatorControllerError::RealtimeClock,
+                ElevatorControllerError::InvalidPassenger {
+                    origin: 1,
+                    destination: 1,
+                },
+            ]
+            .into_iter()
+            .chain(elevator_errors().into_iter().map(Into::into))
+            .collect::<Vec<_>>()
+        };
+        let floor_errors = || {
+            vec![FloorError::GetPanelError {
+                elevator_id: 3,
+                limit: 1,
+            }]
+            .into_iter()
+            .chain(elevator_errors().into_iter().map(Into::into))
+            .chain(controller_errors().into_iter().map(Into::into))
+            .collect::<Vec<_>>()
+        };
+        let serialized_code =
+            |value: serde_json::Value| value["code"].as_str().unwrap().to_string();
+        for err in elevator_errors() {
+            assert_eq!(serialized_code(json!(err)), err.code());
+        }
+        for err in controller_errors() {
+            assert_eq!(serialized_code(json!(err)), err.code());
+        }
+        for err in floor_errors() {
+            assert_eq!(serialized_code(json!(err)), err.code());
+        }
+        let building_errors = std::iter::once(BuildingError::GetFloorError { floor: 7, limit: 4 })
+            .chain(floor_errors().into_iter().map(Into::into))
+            .chain(controller_errors().into_iter().map(Into::into));
+        for err in building_errors {
+            assert_eq!(serialized_code(json!(err)), err.code());
+        }
+        let simulation_errors = std::iter::once(SimulationError::InvalidArrival {
+            origin: 1,
+            destination: 1,
+        })
+        .chain(controller_errors().into_iter().map(Into::into));
+        for err in simulation_errors {
+            assert_eq!(serialized_code(json!(err)), err.code());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_car_call_leaves_hall_call_to_its_car() {
+        let controller = ElevatorController::new(0, 10, 0, 2).with_clock(Clock::manual());
+        let (elevator_0, elevator_1) = (
+            controller.get_elevator(0).unwrap(),
+            controller.get_elevator(1).unwrap(),
+        );
+
+        controller
+            .request_elevator(
+                0,
+                ElevatorAction::Request {
+                    floor: 5,
+                    cancel: false,
+                },
+            )
+            .await
+            .unwrap();
+        controller.advance(Duration::from_secs(3)).await.unwrap();
+        elevator_1.call(5, Direction::Up).await.unwrap();
+
+        // car 0 stops at floor 5 for its car call only, the hall call stays with car 1
+        while !(elevator_0.floor.val() == 5 && elevator_0.is_door_open()) {
+            controller
+                .advance(Duration::from_millis(100))
+                .await
+                .unwrap();
+        }
+        let button = controller.get_outside_button(5).unwrap();
+        assert!(button.is_up());
+        assert!(elevator_1.snapshot().await.targets.contains(&5));
+        assert_eq!(
+            controller.hall_call(5, Direction::Up).await.unwrap(),
+            Registration::AlreadyRegistered
+        );
+        assert_eq!(controller.stats().await.hall_calls_answered, 0);
+
+        controller.advance(Duration::from_secs(30)).await.unwrap();
+        assert!(!button.is_up());
+        let stats = controller.stats().await;
+        assert_eq!(
+            (stats.hall_calls_answered, stats.hall_calls_pending),
+            (1, 0)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_hall_call_held_by_one_car() {
+        let controller = ElevatorController::new(0, 5, 0, 2).with_clock(Clock::manual());
+        let (elevator_0, elevator_1) = (
+            controller.get_elevator(0).unwrap(),
+            controller.get_elevator(1).unwrap(),
+        );
+
+        elevator_0.call(3, Direction::Up).await.unwrap();
+        assert!(matches!(
+            elevator_1.call(3, Direction::Up).await,
+            Err(ElevatorError::HallCallAssigned {
+                floor: 3,
+                direction: Direction::Up
+            })
+        ));
+        assert_eq!(
+            elevator_0.call(3, Direction::Up).await.unwrap(),
+            Registration::AlreadyRegistered
+        );
+        assert!(!elevator_1.has_stops().await);
     }
 }
@@ -0,0 +1,45 @@
+use std::{sync::Arc, time::Duration};
+
+use super::{
+    atomic::{AtomicOperation, AtomicValue, U64},
+    common::timestamp_ms,
+};
+
+/// Source of time for the controller and its cars.
+///
+/// A real-time clock follows the system time and cars drive themselves from
+/// their own task. A manual clock only moves when it is advanced, the owner
+/// then steps the cars explicitly, so a run is reproducible and takes no longer
+/// than the computation.
+#[derive(Debug, Clone, Default)]
+pub enum Clock {
+    #[default]
+    Realtime,
+    Manual(Arc<U64>),
+}
+
+impl Clock {
+    /// Creates a manual clock starting at zero.
+    pub fn manual() -> Self {
+        Self::Manual(Default::default())
+    }
+
+    pub fn is_manual(&self) -> bool {
+        matches!(self, Self::Manual(_))
+    }
+
+    /// Current time, in milliseconds.
+    pub fn now_ms(&self) -> u64 {
+        match self {
+            Self::Realtime => timestamp_ms(),
+            Self::Manual(now) => now.val(),
+        }
+    }
+
+    /// Moves a manual clock forward, a real-time clock cannot be advanced.
+    pub fn advance(&self, duration: Duration) {
+        if let Self::Manual(now) = self {
+            now.add(duration.as_millis() as u64);
+        }
+    }
+}
@@ -4,3 +4,10 @@ pub fn timestamp() -> u64 {
         .expect("system time before Unix epoch")
         .as_secs()
 }
+
+pub fn timestamp_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .expect("system time before Unix epoch")
+        .as_millis() as u64
+}
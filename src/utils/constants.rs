@@ -1,2 +1,25 @@
-pub const MAX_DOOR_OPEN_SECS: u64 = 3;
+pub const DEFAULT_DOOR_OPEN_MS: u64 = 1500;
+pub const DEFAULT_DOOR_CLOSE_MS: u64 = 2000;
+pub const DEFAULT_DOOR_DWELL_MS: u64 = 3000;
+pub const DEFAULT_DOOR_NUDGING_CLOSE_MS: u64 = 6000;
+pub const DEFAULT_DOOR_MAX_REOPENS: u8 = 3;
 pub const ELEVATOR_SELF_CHECK_MS: u64 = 20;
+pub const DEFAULT_FLOOR_HEIGHT: f64 = 3.0;
+pub const DEFAULT_RATED_SPEED: f64 = 2.0;
+pub const DEFAULT_ACCELERATION: f64 = 1.2;
+pub const DEFAULT_JERK: f64 = 2.0;
+pub const ARRIVAL_TOLERANCE: f64 = 0.005;
+pub const ARRIVAL_SPEED_TOLERANCE: f64 = 0.05;
+pub const DISPATCH_STOP_PENALTY: u32 = 2;
+pub const DEFAULT_SIMULATION_TIME_LIMIT_MS: u64 = 24 * 60 * 60 * 1000;
+pub const TRAFFIC_RATE_PERIOD_MS: u64 = 5 * 60 * 1000;
+pub const DEFAULT_RATED_LOAD_KG: f64 = 1000.0;
+pub const DEFAULT_RATED_PERSONS: u8 = 13;
+pub const DEFAULT_FULL_LOAD_RATIO: f64 = 0.8;
+pub const DEFAULT_PASSENGER_WEIGHT_KG: f64 = 75.0;
+pub const DISPATCH_LOAD_PENALTY: u32 = 10;
+pub const DEFAULT_RECOVERY_SPEED: f64 = 0.3;
+pub const EVENT_HISTORY_LEN: usize = 1024;
+pub const ARRIVED_PASSENGER_HISTORY_LEN: usize = 1024;
+/// Latest hall call waits kept for the percentiles, as many as the arrived passengers.
+pub const HALL_CALL_WAIT_SAMPLES_LEN: usize = ARRIVED_PASSENGER_HISTORY_LEN;